@@ -0,0 +1,694 @@
+mod usage;
+
+use super::*;
+use self::usage::*;
+use std::cmp;
+
+/// A kind of warning, with a stable code, like `W0003`, and a name, like `unused-input`, either
+/// of which can be used to allow or deny it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    UnreachableClause,
+    RedundantDefault,
+    UnusedInput,
+    UnusedFunction,
+    UnusedParameter,
+    OverwrittenVariable,
+    DivisionByZero,
+    ShadowedFunction,
+}
+
+pub const LINTS: [Lint; 8] = [
+    Lint::UnreachableClause,
+    Lint::RedundantDefault,
+    Lint::UnusedInput,
+    Lint::UnusedFunction,
+    Lint::UnusedParameter,
+    Lint::OverwrittenVariable,
+    Lint::DivisionByZero,
+    Lint::ShadowedFunction,
+];
+
+impl Lint {
+    pub fn code(&self) -> &'static str {
+        match *self {
+            Lint::UnreachableClause => "W0001",
+            Lint::RedundantDefault => "W0002",
+            Lint::UnusedInput => "W0003",
+            Lint::UnusedFunction => "W0004",
+            Lint::UnusedParameter => "W0005",
+            Lint::OverwrittenVariable => "W0006",
+            Lint::DivisionByZero => "W0007",
+            Lint::ShadowedFunction => "W0008",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Lint::UnreachableClause => "unreachable-clause",
+            Lint::RedundantDefault => "redundant-default",
+            Lint::UnusedInput => "unused-input",
+            Lint::UnusedFunction => "unused-function",
+            Lint::UnusedParameter => "unused-parameter",
+            Lint::OverwrittenVariable => "overwritten-variable",
+            Lint::DivisionByZero => "division-by-zero",
+            Lint::ShadowedFunction => "shadowed-function",
+        }
+    }
+
+    /// The lint with the code or name given.
+    pub fn named(code_or_name: &str) -> Option<Lint> {
+        LINTS
+            .iter()
+            .cloned()
+            .find(|lint| lint.code() == code_or_name || lint.name() == code_or_name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LintLevel {
+    /// The warning isn't given.
+    Allow,
+    Warn,
+    /// The warning is an error.
+    Deny,
+}
+
+/// What to do about each lint. Lints are warned about unless given a level of their own, or
+/// denied if `deny_warnings` is set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lints {
+    levels: HashMap<Lint, LintLevel>,
+    pub deny_warnings: bool,
+}
+
+impl Lints {
+    pub fn new() -> Lints {
+        Lints::default()
+    }
+
+    pub fn set(&mut self, lint: Lint, level: LintLevel) {
+        self.levels.insert(lint, level);
+    }
+
+    pub fn level(&self, lint: Lint) -> LintLevel {
+        match self.levels.get(&lint) {
+            Some(&level) => level,
+            None if self.deny_warnings => LintLevel::Deny,
+            None => LintLevel::Warn,
+        }
+    }
+
+    /// Takes `--allow <lint>`, `--warn <lint>`, `--deny <lint>` and `--deny-warnings` out of
+    /// command line arguments, giving the lints they configure and the other arguments.
+    pub fn from_args<I>(args: I) -> Result<(Lints, Vec<String>), String>
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut lints = Lints::new();
+        let mut rest = vec![];
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let level = match arg.as_str() {
+                "--allow" => LintLevel::Allow,
+                "--warn" => LintLevel::Warn,
+                "--deny" => LintLevel::Deny,
+                "--deny-warnings" => {
+                    lints.deny_warnings = true;
+                    continue;
+                }
+                _ => {
+                    rest.push(arg);
+                    continue;
+                }
+            };
+            let lint = match args.next() {
+                Some(lint) => lint,
+                None => return Err(format!("`{}` needs a lint code or name", arg)),
+            };
+            match Lint::named(&lint) {
+                Some(lint) => lints.set(lint, level),
+                None => return Err(format!("unknown lint `{}`", lint)),
+            }
+        }
+        Ok((lints, rest))
+    }
+}
+
+/// Something suspicious about a program that doesn't stop it running.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// A `match` clause that only matches values already matched by earlier unguarded clauses,
+    /// so can never be used. `name` is the variable or function whose definition contains it.
+    UnreachableClause { name: Name, matcher: Matcher },
+    /// A `match` whose unguarded clauses match every value, so its `_` clause is never used.
+    RedundantDefault { name: Name },
+    /// An input that is never read.
+    UnusedInput(Name),
+    /// A function that no variable's value is worked out with, even indirectly.
+    UnusedFunction(Name),
+    /// A parameter that `function` never reads.
+    UnusedParameter { function: Name, name: Name },
+    /// A variable assigned again before the value it was assigned here is read.
+    OverwrittenVariable(Name),
+    /// Dividing by a constant zero, which doesn't fail. `name` is the variable or function whose
+    /// definition contains it, and `span` where the zero is.
+    DivisionByZero {
+        name: Name,
+        operator: Operator,
+        span: Span,
+    },
+    /// A function defined again, hiding its earlier definition from everything after.
+    ShadowedFunction(Name),
+}
+
+impl Warning {
+    pub fn lint(&self) -> Lint {
+        match *self {
+            Warning::UnreachableClause { .. } => Lint::UnreachableClause,
+            Warning::RedundantDefault { .. } => Lint::RedundantDefault,
+            Warning::UnusedInput(_) => Lint::UnusedInput,
+            Warning::UnusedFunction(_) => Lint::UnusedFunction,
+            Warning::UnusedParameter { .. } => Lint::UnusedParameter,
+            Warning::OverwrittenVariable(_) => Lint::OverwrittenVariable,
+            Warning::DivisionByZero { .. } => Lint::DivisionByZero,
+            Warning::ShadowedFunction(_) => Lint::ShadowedFunction,
+        }
+    }
+
+    /// Where the name or expression the warning is about was written.
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            Warning::UnreachableClause { ref name, .. }
+            | Warning::RedundantDefault { ref name }
+            | Warning::UnusedInput(ref name)
+            | Warning::UnusedFunction(ref name)
+            | Warning::UnusedParameter { ref name, .. }
+            | Warning::OverwrittenVariable(ref name)
+            | Warning::ShadowedFunction(ref name) => located(name.1),
+            Warning::DivisionByZero { span, .. } => located(span),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::UnreachableClause {
+                ref name,
+                ref matcher,
+            } => write!(
+                f,
+                "unreachable match clause `{}` in `{}`: its values are already matched",
+                matcher, name
+            ),
+            Warning::RedundantDefault { ref name } => write!(
+                f,
+                "redundant `_` clause in `{}`: every value is already matched",
+                name
+            ),
+            Warning::UnusedInput(ref name) => write!(f, "input `{}` is never used", name),
+            Warning::UnusedFunction(ref name) => write!(f, "function `{}` is never used", name),
+            Warning::UnusedParameter {
+                ref function,
+                ref name,
+            } => write!(f, "parameter `{}` of `{}` is never used", name, function),
+            Warning::OverwrittenVariable(ref name) => {
+                write!(f, "`{}` is assigned again before this value is read", name)
+            }
+            Warning::DivisionByZero {
+                ref name,
+                operator: Operator::Divide,
+                ..
+            } => write!(f, "division by zero in `{}` saturates instead of failing", name),
+            Warning::DivisionByZero {
+                ref name,
+                operator,
+                ..
+            } => write!(
+                f,
+                "`{}` by zero in `{}` gives the numerator instead of failing",
+                operator, name
+            ),
+            Warning::ShadowedFunction(ref name) => {
+                write!(f, "`{}` is defined again, hiding its earlier definition", name)
+            }
+        }
+    }
+}
+
+/// Everything any lint warns about, in the order it was written.
+pub fn warnings(program: &Program) -> Vec<Warning> {
+    let mut warnings = vec![];
+    for statement in &program.statements.0 {
+        match *statement {
+            Statement::VarAssignment(ref name, ref expression, _) => {
+                expression_warnings(name, expression, &mut warnings)
+            }
+            Statement::FnDefinition(ref name, _, ref expression, ref bindings, _) => {
+                for &(_, ref binding) in bindings {
+                    expression_warnings(name, binding, &mut warnings);
+                }
+                expression_warnings(name, expression, &mut warnings);
+            }
+        }
+    }
+    warnings.extend(usage_warnings(program));
+    warnings.sort_by_key(|warning| warning.span().map(|span| span.start));
+    warnings
+}
+
+fn expression_warnings(name: &Name, expression: &Expression, warnings: &mut Vec<Warning>) {
+    match *expression {
+        Expression::Operand(ref operand) => operand_warnings(name, operand, warnings),
+        Expression::Operation(operator, ref expression1, ref expression2) => {
+            let divides = match operator {
+                Operator::Divide | Operator::Remainder | Operator::Modulo => true,
+                _ => false,
+            };
+            if divides && interpreter::evaluate(expression2) == Ok(0) {
+                warnings.push(Warning::DivisionByZero {
+                    name: name.clone(),
+                    operator: operator,
+                    span: expression2.span(),
+                });
+            }
+            expression_warnings(name, expression1, warnings);
+            expression_warnings(name, expression2, warnings);
+        }
+        Expression::Spanned(_, ref expression) => expression_warnings(name, expression, warnings),
+    }
+}
+
+fn operand_warnings(name: &Name, operand: &Operand, warnings: &mut Vec<Warning>) {
+    match *operand {
+        Operand::I64(_) | Operand::VarSubstitution(_) => {}
+        Operand::Group(ref expression) => expression_warnings(name, expression, warnings),
+        Operand::FnApplication(_, ref args) => for arg in args {
+            expression_warnings(name, arg, warnings);
+        },
+        Operand::Match(ref match_) => match_warnings(name, match_, warnings),
+        Operand::If(ref condition, ref then, ref else_) => {
+            expression_warnings(name, condition, warnings);
+            expression_warnings(name, then, warnings);
+            expression_warnings(name, else_, warnings);
+        }
+        Operand::Not(ref operand) | Operand::Negate(ref operand) => {
+            operand_warnings(name, operand, warnings)
+        }
+        Operand::Let(ref bindings, ref body) => {
+            for &(_, ref binding) in bindings {
+                expression_warnings(name, binding, warnings);
+            }
+            expression_warnings(name, body, warnings);
+        }
+    }
+}
+
+fn match_warnings(name: &Name, match_: &Match, warnings: &mut Vec<Warning>) {
+    for expression in &match_.with {
+        expression_warnings(name, expression, warnings);
+    }
+    for i in unreachable_clauses(match_) {
+        warnings.push(Warning::UnreachableClause {
+            name: name.clone(),
+            matcher: match_.clauses[i].0.clone(),
+        });
+    }
+    if !is_default_reachable(match_) {
+        warnings.push(Warning::RedundantDefault { name: name.clone() });
+    }
+    for &(ref matcher, ref guard, ref expression) in &match_.clauses {
+        matcher_warnings(name, matcher, warnings);
+        if let Some(ref guard) = *guard {
+            expression_warnings(name, guard, warnings);
+        }
+        expression_warnings(name, expression, warnings);
+    }
+    expression_warnings(name, &match_.default, warnings);
+}
+
+fn matcher_warnings(name: &Name, matcher: &Matcher, warnings: &mut Vec<Warning>) {
+    match *matcher {
+        Matcher::Value(ref expression) => expression_warnings(name, expression, warnings),
+        Matcher::Range { ref start, ref end, .. } => {
+            expression_warnings(name, start, warnings);
+            expression_warnings(name, end, warnings);
+        }
+        Matcher::Alternatives(ref matchers) | Matcher::Tuple(ref matchers) => {
+            for matcher in matchers {
+                matcher_warnings(name, matcher, warnings);
+            }
+        }
+        Matcher::Wildcard => {}
+        Matcher::Binding(_, ref matcher) => matcher_warnings(name, matcher, warnings),
+    }
+}
+
+/// The indices of clauses that only match values already matched by earlier unguarded clauses.
+/// Matchers are only understood when built from constants, so this may miss some.
+pub fn unreachable_clauses(match_: &Match) -> Vec<usize> {
+    let mut unreachable = vec![];
+    let mut matched: Vec<Vec<Values>> = vec![];
+    for (i, &(ref matcher, ref guard, _)) in match_.clauses.iter().enumerate() {
+        let values = element_values(matcher, match_.with.len());
+        if values.iter().all(|values| values.exact) && is_matched(&matched, &values) {
+            unreachable.push(i);
+        }
+        if guard.is_none() {
+            matched.push(values);
+        }
+    }
+    unreachable
+}
+
+/// Whether any value might not be matched by the clauses, and so fall to the default.
+pub fn is_default_reachable(match_: &Match) -> bool {
+    let mut matched: Vec<Vec<Values>> = vec![];
+    for &(ref matcher, ref guard, _) in &match_.clauses {
+        if guard.is_none() {
+            matched.push(element_values(matcher, match_.with.len()));
+        }
+    }
+    let everything = Values {
+        ranges: vec![(i64::min_value(), i64::max_value())],
+        exact: true,
+    };
+    !is_matched(&matched, &vec![everything; match_.with.len()])
+}
+
+// The values a matcher matches, as inclusive ranges. If not `exact`, it may match more.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Values {
+    ranges: Vec<(i64, i64)>,
+    exact: bool,
+}
+
+// The values matched at each position of a match on `arity` values.
+fn element_values(matcher: &Matcher, arity: usize) -> Vec<Values> {
+    match *matcher {
+        Matcher::Tuple(ref matchers) => matchers.iter().map(values).collect(),
+        ref matcher => vec![values(matcher); arity],
+    }
+}
+
+fn values(matcher: &Matcher) -> Values {
+    let unknown = Values {
+        ranges: vec![],
+        exact: false,
+    };
+    match *matcher {
+        Matcher::Value(ref expression) => match interpreter::evaluate(expression) {
+            Ok(value) => Values {
+                ranges: vec![(value, value)],
+                exact: true,
+            },
+            Err(_) => unknown,
+        },
+        Matcher::Range {
+            ref start,
+            ref end,
+            inclusive,
+        } => match (interpreter::evaluate(start), interpreter::evaluate(end)) {
+            (Ok(start), Ok(end)) => {
+                let end = if inclusive { Some(end) } else { end.checked_sub(1) };
+                Values {
+                    ranges: match end {
+                        Some(end) if start <= end => vec![(start, end)],
+                        _ => vec![],
+                    },
+                    exact: true,
+                }
+            }
+            _ => unknown,
+        },
+        Matcher::Alternatives(ref matchers) => {
+            let alternatives: Vec<_> = matchers.iter().map(values).collect();
+            Values {
+                ranges: alternatives
+                    .iter()
+                    .flat_map(|values| values.ranges.iter().cloned())
+                    .collect(),
+                exact: alternatives.iter().all(|values| values.exact),
+            }
+        }
+        Matcher::Wildcard => Values {
+            ranges: vec![(i64::min_value(), i64::max_value())],
+            exact: true,
+        },
+        Matcher::Binding(_, ref matcher) => values(matcher),
+        // Only used for a clause's whole matcher, so handled by `element_values`.
+        Matcher::Tuple(_) => unknown,
+    }
+}
+
+// Whether all of `values` are matched by the clauses `matched`. Single values are checked against
+// all the clauses together, but tuples only against one clause at a time.
+fn is_matched(matched: &Vec<Vec<Values>>, values: &Vec<Values>) -> bool {
+    if values.len() == 1 {
+        let ranges = matched
+            .iter()
+            .flat_map(|clause| clause[0].ranges.iter().cloned())
+            .collect();
+        return covers(ranges, &values[0].ranges);
+    }
+    matched.iter().any(|clause| {
+        clause
+            .iter()
+            .zip(values)
+            .all(|(clause, values)| covers(clause.ranges.clone(), &values.ranges))
+    })
+}
+
+fn covers(mut ranges: Vec<(i64, i64)>, covered: &Vec<(i64, i64)>) -> bool {
+    ranges.sort();
+    let mut merged: Vec<(i64, i64)> = vec![];
+    for (start, end) in ranges {
+        if let Some(last) = merged.last_mut() {
+            if last.1 == i64::max_value() || start <= last.1 + 1 {
+                last.1 = cmp::max(last.1, end);
+                continue;
+            }
+        }
+        merged.push((start, end));
+    }
+    covered.iter().all(|&(start, end)| {
+        merged
+            .iter()
+            .any(|&(merged_start, merged_end)| merged_start <= start && end <= merged_end)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parser::{self, parse};
+    use quickcheck::{QuickCheck, StdGen};
+    use rand::thread_rng;
+
+    fn warnings_of(statements: &str) -> Vec<Warning> {
+        lint_warnings(&format!("inputs; {} outputs;", statements))
+            .into_iter()
+            .filter(|warning| match warning.lint() {
+                Lint::UnreachableClause | Lint::RedundantDefault => true,
+                _ => false,
+            })
+            .collect()
+    }
+
+    fn lint_warnings(program: &str) -> Vec<Warning> {
+        warnings(&parse(program.as_bytes()).unwrap())
+    }
+
+    fn unreachable(name: &str, matcher: &str) -> Warning {
+        Warning::UnreachableClause {
+            name: Name::new(name),
+            matcher: parser::matcher(format!("{} =>", matcher).as_bytes()).unwrap().1,
+        }
+    }
+
+    #[test]
+    fn duplicate_values_are_unreachable() {
+        assert_eq!(
+            warnings_of("x = match 1 { 1 => 1, 2 | 3 => 2, 1 + 2 => 3, 4 => 4, _ => 0 };"),
+            vec![unreachable("x", "1 + 2")]
+        );
+    }
+
+    #[test]
+    fn shadowed_ranges_are_unreachable() {
+        assert_eq!(
+            warnings_of(
+                "f(n) = match n { 0..10 => 1, 10 => 2, 5..=9 => 3, -1 | 0 => 4, 20..20 => 5, \
+                 _ => 0 };"
+            ),
+            vec![unreachable("f", "5..=9"), unreachable("f", "20..20")]
+        );
+    }
+
+    #[test]
+    fn guarded_clauses_do_not_shadow() {
+        assert_eq!(
+            warnings_of("f(n) = match n { 1 if n > 0 => 1, 1 => 2, let m if m > 3 => m, _ => 0 };"),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn unknown_values_do_not_shadow() {
+        assert_eq!(
+            warnings_of("f(n, m) = match n { m => 1, 1 | m => 2, 1 => 3, m => 4, _ => 0 };"),
+            vec![unreachable("f", "1")]
+        );
+    }
+
+    #[test]
+    fn catch_all_clauses_make_the_default_redundant() {
+        assert_eq!(
+            warnings_of("f(n) = match n { let m => m, 1 => 1, _ => 0 };"),
+            vec![
+                unreachable("f", "1"),
+                Warning::RedundantDefault { name: Name::new("f") },
+            ]
+        );
+        assert_eq!(
+            warnings_of(
+                "x = match 0 { -9223372036854775807-1..0 => 1, 0..=9223372036854775807 => 2, \
+                 _ => 0 };"
+            ),
+            vec![Warning::RedundantDefault { name: Name::new("x") }]
+        );
+    }
+
+    #[test]
+    fn tuple_clauses_are_shadowed_by_single_clauses() {
+        assert_eq!(
+            warnings_of(
+                "f(m, n) = match (m, n) { (0, _) => 1, (0, 1) => 2, (1, 1) => 3, (_, 1) => 4, \
+                 (1, 0..2) => 5, (let a, let b) => 6, _ => 0 };"
+            ),
+            vec![
+                unreachable("f", "(0, 1)"),
+                Warning::RedundantDefault { name: Name::new("f") },
+            ]
+        );
+    }
+
+    #[test]
+    fn nested_matches_are_checked() {
+        assert_eq!(
+            warnings_of(
+                "f(n) = g(match n { 1 => 1, 1 => 2, _ => 0 }) + m \
+                 where m = match n { 2 => 1, 2 => 2, _ => 0 };"
+            ),
+            vec![unreachable("f", "2"), unreachable("f", "1")]
+        );
+    }
+
+    #[test]
+    fn unused_names_are_found() {
+        assert_eq!(
+            lint_warnings(
+                "inputs a, b, _c; f(n, m) = n; g(n) = n where n = 1; h(n) = f(n, n) + h(n); \
+                 k(_n) = f(1, 2); x = k(a); outputs x;"
+            ),
+            vec![
+                Warning::UnusedInput(Name::new("b")),
+                Warning::UnusedParameter {
+                    function: Name::new("f"),
+                    name: Name::new("m"),
+                },
+                Warning::UnusedFunction(Name::new("g")),
+                Warning::UnusedParameter {
+                    function: Name::new("g"),
+                    name: Name::new("n"),
+                },
+                Warning::UnusedFunction(Name::new("h")),
+            ]
+        );
+    }
+
+    #[test]
+    fn overwritten_variables_are_found() {
+        assert_eq!(
+            lint_warnings(
+                "inputs a, b; a = 1; x = 2; x = let x = 3 in x; y = b; y = y + match 1 { \
+                 let x if x > 0 => x, _ => 0 }; x = y; outputs x;"
+            ),
+            vec![
+                Warning::UnusedInput(Name::new("a")),
+                Warning::OverwrittenVariable(Name::new("x")),
+                Warning::OverwrittenVariable(Name::new("x")),
+            ]
+        );
+    }
+
+    #[test]
+    fn divisions_by_zero_are_found() {
+        let division = |operator| Warning::DivisionByZero {
+            name: Name::new("x"),
+            operator: operator,
+            span: Span::default(),
+        };
+        assert_eq!(
+            lint_warnings("inputs a; x = a / 0 + a % (1 - 1) + a mod 0 + 0 / a; outputs x;"),
+            vec![
+                division(Operator::Divide),
+                division(Operator::Remainder),
+                division(Operator::Modulo),
+            ]
+        );
+    }
+
+    #[test]
+    fn shadowed_functions_are_found() {
+        assert_eq!(
+            lint_warnings("inputs; f() = 1; x = f(); f() = 2; y = f(); outputs x, y;"),
+            vec![Warning::ShadowedFunction(Name::new("f"))]
+        );
+        assert_eq!(
+            lint_warnings("inputs; f(n) = n; f(m) = m + 1; x = f(1); outputs x;"),
+            vec![Warning::ShadowedFunction(Name::new("f"))]
+        );
+    }
+
+    #[test]
+    fn lints_are_configured() {
+        assert_eq!(Lint::named("W0003"), Some(Lint::UnusedInput));
+        assert_eq!(Lint::named("unused-input"), Some(Lint::UnusedInput));
+        assert_eq!(Lint::named("unused"), None);
+
+        let args = vec!["--allow", "W0001", "5", "--deny-warnings", "--warn", "unused-input", "-1"];
+        let (lints, rest) = Lints::from_args(args.into_iter().map(String::from)).unwrap();
+        assert_eq!(rest, vec!["5", "-1"]);
+        assert_eq!(lints.level(Lint::UnreachableClause), LintLevel::Allow);
+        assert_eq!(lints.level(Lint::UnusedInput), LintLevel::Warn);
+        assert_eq!(lints.level(Lint::DivisionByZero), LintLevel::Deny);
+
+        let args = vec!["--deny", "unused"];
+        assert_eq!(
+            Lints::from_args(args.into_iter().map(String::from)),
+            Err("unknown lint `unused`".to_string())
+        );
+        assert_eq!(
+            Lints::from_args(vec!["--deny".to_string()]),
+            Err("`--deny` needs a lint code or name".to_string())
+        );
+    }
+
+    fn generated_programs_have_no_warnings_prop(program: Program) -> bool {
+        // Programs are only generated to avoid the clauses these warn about.
+        warnings(&program).iter().all(|warning| match warning.lint() {
+            Lint::UnreachableClause | Lint::RedundantDefault => false,
+            _ => true,
+        })
+    }
+
+    #[test]
+    fn generated_programs_have_no_warnings() {
+        for size in 1..11 {
+            let mut qc = QuickCheck::new().gen(StdGen::new(thread_rng(), size));
+            qc.quickcheck(generated_programs_have_no_warnings_prop as fn(Program) -> bool);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use super::*;
+
+// The latest value of a variable: where it was assigned, whether it's an input, and whether it
+// has been read since.
+struct Assignment {
+    name: Name,
+    input: bool,
+    read: bool,
+}
+
+// The variables an expression reads, other than those it binds itself, and the functions it
+// calls.
+#[derive(Default)]
+struct Uses {
+    variables: HashSet<Name>,
+    functions: HashSet<Name>,
+}
+
+/// Warns about inputs, functions, parameters and assignments that are never used, and functions
+/// that are defined again, whose earlier definitions aren't also warned about as unused. Names
+/// starting with `_` are never warned about as unused.
+pub fn usage_warnings(program: &Program) -> Vec<Warning> {
+    let mut warnings = vec![];
+    let mut assignments = HashMap::new();
+    for input in &program.inputs {
+        let assignment = Assignment {
+            name: input.clone(),
+            input: true,
+            read: false,
+        };
+        assignments.insert(input.clone(), assignment);
+    }
+    // Each function definition with the definitions it calls, the current definition of each
+    // function, and the definitions called to work out variables.
+    let mut definitions: Vec<(Name, Vec<usize>)> = vec![];
+    let mut current = HashMap::new();
+    let mut called = vec![];
+    // The definitions of functions defined again, which are warned about for that instead of
+    // for being unused.
+    let mut shadowed = HashSet::new();
+    for statement in &program.statements.0 {
+        match *statement {
+            Statement::VarAssignment(ref name, ref expression, _) => {
+                let mut uses = Uses::default();
+                expression_uses(expression, &HashSet::new(), &mut uses);
+                for variable in &uses.variables {
+                    if let Some(assignment) = assignments.get_mut(variable) {
+                        assignment.read = true;
+                    }
+                }
+                called.extend(uses.functions.iter().filter_map(|f| current.get(f).cloned()));
+                let assignment = Assignment {
+                    name: name.clone(),
+                    input: false,
+                    read: false,
+                };
+                if let Some(previous) = assignments.insert(name.clone(), assignment) {
+                    warnings.extend(unread_warning(previous));
+                }
+            }
+            Statement::FnDefinition(ref name, ref params, ref body, ref bindings, _) => {
+                if let Some(&previous) = current.get(name) {
+                    warnings.push(Warning::ShadowedFunction(name.clone()));
+                    shadowed.insert(previous);
+                }
+                current.insert(name.clone(), definitions.len());
+                let mut uses = Uses::default();
+                let mut bound = HashSet::new();
+                for &(ref binding, ref value) in bindings {
+                    expression_uses(value, &bound, &mut uses);
+                    bound.insert(binding.clone());
+                }
+                expression_uses(body, &bound, &mut uses);
+                for param in params {
+                    if !uses.variables.contains(param) && !param.0.starts_with('_') {
+                        warnings.push(Warning::UnusedParameter {
+                            function: name.clone(),
+                            name: param.clone(),
+                        });
+                    }
+                }
+                let calls = uses.functions.iter().filter_map(|f| current.get(f).cloned());
+                definitions.push((name.clone(), calls.collect()));
+            }
+        }
+    }
+    for output in &program.outputs {
+        if let Some(assignment) = assignments.get_mut(output) {
+            assignment.read = true;
+        }
+    }
+    for input in &program.inputs {
+        if let Some(assignment) = assignments.remove(input) {
+            if assignment.input {
+                warnings.extend(unread_warning(assignment));
+            }
+        }
+    }
+
+    let mut reached = vec![false; definitions.len()];
+    while let Some(i) = called.pop() {
+        if !reached[i] {
+            reached[i] = true;
+            called.extend(definitions[i].1.iter().cloned());
+        }
+    }
+    for (i, &(ref name, _)) in definitions.iter().enumerate() {
+        if !reached[i] && !shadowed.contains(&i) && !name.0.starts_with('_') {
+            warnings.push(Warning::UnusedFunction(name.clone()));
+        }
+    }
+    warnings
+}
+
+// The warning about a value that was assigned again, or an input at the end, if it wasn't read.
+fn unread_warning(assignment: Assignment) -> Option<Warning> {
+    if assignment.read || assignment.name.0.starts_with('_') {
+        None
+    } else if assignment.input {
+        Some(Warning::UnusedInput(assignment.name))
+    } else {
+        Some(Warning::OverwrittenVariable(assignment.name))
+    }
+}
+
+fn expression_uses(expression: &Expression, bound: &HashSet<Name>, uses: &mut Uses) {
+    match *expression {
+        Expression::Operand(ref operand) => operand_uses(operand, bound, uses),
+        Expression::Operation(_, ref expression1, ref expression2) => {
+            expression_uses(expression1, bound, uses);
+            expression_uses(expression2, bound, uses);
+        }
+        Expression::Spanned(_, ref expression) => expression_uses(expression, bound, uses),
+    }
+}
+
+fn operand_uses(operand: &Operand, bound: &HashSet<Name>, uses: &mut Uses) {
+    match *operand {
+        Operand::I64(_) => {}
+        Operand::Group(ref expression) => expression_uses(expression, bound, uses),
+        Operand::VarSubstitution(ref name) => if !bound.contains(name) {
+            uses.variables.insert(name.clone());
+        },
+        Operand::FnApplication(ref name, ref args) => {
+            uses.functions.insert(name.clone());
+            for arg in args {
+                expression_uses(arg, bound, uses);
+            }
+        }
+        Operand::Match(ref match_) => {
+            for expression in &match_.with {
+                expression_uses(expression, bound, uses);
+            }
+            for &(ref matcher, ref guard, ref expression) in &match_.clauses {
+                matcher_uses(matcher, bound, uses);
+                let mut scope = bound.clone();
+                scope.extend(matcher.bindings().into_iter().map(|(_, name)| name));
+                if let Some(ref guard) = *guard {
+                    expression_uses(guard, &scope, uses);
+                }
+                expression_uses(expression, &scope, uses);
+            }
+            expression_uses(&match_.default, bound, uses);
+        }
+        Operand::If(ref condition, ref then, ref else_) => {
+            expression_uses(condition, bound, uses);
+            expression_uses(then, bound, uses);
+            expression_uses(else_, bound, uses);
+        }
+        Operand::Not(ref operand) | Operand::Negate(ref operand) => {
+            operand_uses(operand, bound, uses)
+        }
+        Operand::Let(ref bindings, ref body) => {
+            let mut scope = bound.clone();
+            for &(ref name, ref value) in bindings {
+                expression_uses(value, &scope, uses);
+                scope.insert(name.clone());
+            }
+            expression_uses(body, &scope, uses);
+        }
+    }
+}
+
+fn matcher_uses(matcher: &Matcher, bound: &HashSet<Name>, uses: &mut Uses) {
+    match *matcher {
+        Matcher::Value(ref value) => expression_uses(value, bound, uses),
+        Matcher::Range { ref start, ref end, .. } => {
+            expression_uses(start, bound, uses);
+            expression_uses(end, bound, uses);
+        }
+        Matcher::Alternatives(ref matchers) | Matcher::Tuple(ref matchers) => {
+            for matcher in matchers {
+                matcher_uses(matcher, bound, uses);
+            }
+        }
+        Matcher::Binding(_, ref matcher) => matcher_uses(matcher, bound, uses),
+        Matcher::Wildcard => {}
+    }
+}
@@ -1,23 +1,44 @@
 extern crate math;
 
+use math::analysis::Lints;
+use math::diagnostics::{self, Diagnostic};
 use std::env;
 use std::fs::File;
 use std::io::prelude::*;
+use std::process;
 
 fn main() {
-    let mut args = env::args();
-    args.next().unwrap();
-    let in_path = args.next().unwrap();
-    let out_path = args.next().unwrap();
+    let (lints, args) = Lints::from_args(env::args().skip(1))
+        .unwrap_or_else(|e| fail(&format!("error: {}", e)));
+    let mut args = args.into_iter();
+    let (in_path, out_path) = match (args.next(), args.next(), args.next()) {
+        (Some(in_path), Some(out_path), None) => (in_path, out_path),
+        _ => fail(
+            "usage: mathc [--allow <lint>] [--warn <lint>] [--deny <lint>] [--deny-warnings] \
+             <program> <executable>",
+        ),
+    };
     eprintln!("Compiling {} into {}", in_path, out_path);
 
-    let mut in_file = File::open(in_path).unwrap();
     let mut in_ = String::new();
-    in_file.read_to_string(&mut in_).unwrap();
+    if let Err(e) = File::open(&in_path).and_then(|mut in_file| in_file.read_to_string(&mut in_)) {
+        fail(&format!("error: couldn't read {}: {}", in_path, e));
+    }
 
-    let ir = math::compile(
-        in_.as_bytes(),
-        math::compiler::Emit::Binary(out_path.into()),
-    ).unwrap();
+    let (program, diagnostics) = diagnostics::diagnose_with(in_.as_bytes(), &lints);
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render(&in_path, in_.as_bytes()));
+    }
+    let program = program.unwrap_or_else(|| process::exit(1));
+    let emit = math::compiler::Emit::Binary(out_path.into());
+    let ir = unsafe { math::compiler::compile(&program, emit) }.unwrap_or_else(|e| {
+        let error = Diagnostic::error(&math::Error::CompilerError(e));
+        fail(&error.render(&in_path, in_.as_bytes()))
+    });
     println!("{}", ir);
 }
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
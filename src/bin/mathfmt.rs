@@ -0,0 +1,46 @@
+extern crate math;
+
+use math::diagnostics::Diagnostic;
+use std::env;
+use std::fs::File;
+use std::io::{self, Read};
+use std::process;
+
+fn main() {
+    let usage = "usage: mathfmt [--width <columns>] [<program>]";
+    let mut width = 80;
+    let mut path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--width" {
+            width = match args.next().map(|width| width.parse()) {
+                Some(Ok(width)) => width,
+                _ => fail(usage),
+            };
+        } else if path.is_none() {
+            path = Some(arg);
+        } else {
+            fail(usage);
+        }
+    }
+
+    let mut in_ = String::new();
+    let read = match path {
+        Some(ref path) => File::open(path).and_then(|mut in_file| in_file.read_to_string(&mut in_)),
+        None => io::stdin().read_to_string(&mut in_),
+    };
+    let path = path.unwrap_or_else(|| "<stdin>".to_string());
+    if let Err(e) = read {
+        fail(&format!("error: couldn't read {}: {}", path, e));
+    }
+
+    match math::format_program(in_.as_bytes(), width) {
+        Ok(formatted) => print!("{}", formatted),
+        Err(e) => fail(&Diagnostic::error(&e).render(&path, in_.as_bytes())),
+    }
+}
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
@@ -14,5 +14,6 @@ fn main() {
 
     let rng = StdRng::new().unwrap();
     let mut gen = StdGen::new(rng, size);
-    println!("{}", math::Program::arbitrary(&mut gen));
+    let program = format!("{}", math::Program::arbitrary(&mut gen));
+    print!("{}", math::format_program(program.as_bytes(), 80).unwrap());
 }
@@ -1,18 +1,39 @@
 extern crate math;
 
+use math::analysis::Lints;
+use math::diagnostics::{self, Diagnostic};
 use std::env;
 use std::io::{self, Read};
+use std::process;
 
 fn main() {
     let mut in_ = String::new();
-    io::stdin().read_to_string(&mut in_).unwrap();
+    if let Err(e) = io::stdin().read_to_string(&mut in_) {
+        fail(&format!("error: couldn't read the program: {}", e));
+    }
 
-    let mut args = env::args();
-    args.next().unwrap();
-    let inputs = args.map(|input| input.parse().unwrap()).collect();
+    let (lints, args) = Lints::from_args(env::args().skip(1))
+        .unwrap_or_else(|e| fail(&format!("error: {}", e)));
+    let inputs = args.into_iter().map(|input| match input.parse() {
+        Ok(input) => input,
+        Err(_) => fail(&format!("error: input `{}` isn't an integer", input)),
+    }).collect();
 
-    let outputs = math::interpret(in_.as_bytes(), &inputs).unwrap();
+    let (program, diagnostics) = diagnostics::diagnose_with(in_.as_bytes(), &lints);
+    for diagnostic in diagnostics {
+        eprintln!("{}", diagnostic.render("<stdin>", in_.as_bytes()));
+    }
+    let program = program.unwrap_or_else(|| process::exit(1));
+    let outputs = math::interpreter::execute(&program, &inputs).unwrap_or_else(|e| {
+        let error = Diagnostic::error(&math::Error::InterpreterError(e));
+        fail(&error.render("<stdin>", in_.as_bytes()))
+    });
     for n in outputs {
         println!("{}", n);
     }
 }
+
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
@@ -0,0 +1,267 @@
+use super::*;
+
+/// A mistake that would stop a program running, found without running it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UnknownVariable(Name),
+    UnknownFunction(Name),
+    IncorrectArgumentCount {
+        name: Name,
+        params_count: usize,
+        provided_count: usize,
+    },
+    DuplicateParameter { function: Name, name: Name },
+    UnassignedOutput(Name),
+}
+
+impl Error {
+    /// Where in the source the error is.
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            Error::UnknownVariable(ref name)
+            | Error::UnknownFunction(ref name)
+            | Error::IncorrectArgumentCount { ref name, .. }
+            | Error::DuplicateParameter { ref name, .. }
+            | Error::UnassignedOutput(ref name) => located(name.1),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownVariable(ref name) => write!(f, "unknown variable `{}`", name),
+            Error::UnknownFunction(ref name) => write!(f, "unknown function `{}`", name),
+            Error::IncorrectArgumentCount {
+                ref name,
+                params_count,
+                provided_count,
+            } => write!(
+                f,
+                "`{}` takes {}, but was given {}",
+                name,
+                count(params_count, "argument"),
+                provided_count
+            ),
+            Error::DuplicateParameter {
+                ref function,
+                ref name,
+            } => write!(f, "`{}` has more than one parameter named `{}`", function, name),
+            Error::UnassignedOutput(ref name) => write!(f, "output `{}` is never assigned", name),
+        }
+    }
+}
+
+/// Finds every name that doesn't refer to anything, every call with the wrong number of
+/// arguments, and so on. Function bodies are checked whether or not they are called, and may
+/// only use their parameters and the functions defined before them, or themselves.
+pub fn errors(program: &Program) -> Vec<Error> {
+    let mut errors = vec![];
+    let mut vars: HashSet<Name> = program.inputs.iter().cloned().collect();
+    let mut fns = HashMap::new();
+    for statement in &program.statements.0 {
+        match *statement {
+            Statement::VarAssignment(ref name, ref expression, _) => {
+                expression_errors(expression, &vars, &fns, &mut errors);
+                vars.insert(name.clone());
+            }
+            Statement::FnDefinition(ref name, ref params, ref body, ref bindings, _) => {
+                let mut scope = HashSet::new();
+                for param in params {
+                    if !scope.insert(param.clone()) {
+                        errors.push(Error::DuplicateParameter {
+                            function: name.clone(),
+                            name: param.clone(),
+                        });
+                    }
+                }
+                fns.insert(name.clone(), params.len());
+                for &(ref binding, ref value) in bindings {
+                    expression_errors(value, &scope, &fns, &mut errors);
+                    scope.insert(binding.clone());
+                }
+                expression_errors(body, &scope, &fns, &mut errors);
+            }
+        }
+    }
+    for output in &program.outputs {
+        if !vars.contains(output) {
+            errors.push(Error::UnassignedOutput(output.clone()));
+        }
+    }
+    errors
+}
+
+fn expression_errors(
+    expression: &Expression,
+    vars: &HashSet<Name>,
+    fns: &HashMap<Name, usize>,
+    errors: &mut Vec<Error>,
+) {
+    match *expression {
+        Expression::Operand(ref operand) => operand_errors(operand, vars, fns, errors),
+        Expression::Operation(_, ref expression1, ref expression2) => {
+            expression_errors(expression1, vars, fns, errors);
+            expression_errors(expression2, vars, fns, errors);
+        }
+        Expression::Spanned(_, ref expression) => expression_errors(expression, vars, fns, errors),
+    }
+}
+
+fn operand_errors(
+    operand: &Operand,
+    vars: &HashSet<Name>,
+    fns: &HashMap<Name, usize>,
+    errors: &mut Vec<Error>,
+) {
+    match *operand {
+        Operand::I64(_) => {}
+        Operand::Group(ref expression) => expression_errors(expression, vars, fns, errors),
+        Operand::VarSubstitution(ref name) => if !vars.contains(name) {
+            errors.push(Error::UnknownVariable(name.clone()));
+        },
+        Operand::FnApplication(ref name, ref args) => {
+            match fns.get(name) {
+                None => errors.push(Error::UnknownFunction(name.clone())),
+                Some(&params_count) if params_count != args.len() => {
+                    errors.push(Error::IncorrectArgumentCount {
+                        name: name.clone(),
+                        params_count: params_count,
+                        provided_count: args.len(),
+                    })
+                }
+                Some(_) => {}
+            }
+            for arg in args {
+                expression_errors(arg, vars, fns, errors);
+            }
+        }
+        Operand::Match(ref match_) => {
+            for expression in &match_.with {
+                expression_errors(expression, vars, fns, errors);
+            }
+            for &(ref matcher, ref guard, ref expression) in &match_.clauses {
+                matcher_errors(matcher, vars, fns, errors);
+                let mut scope = vars.clone();
+                scope.extend(matcher.bindings().into_iter().map(|(_, name)| name));
+                if let Some(ref guard) = *guard {
+                    expression_errors(guard, &scope, fns, errors);
+                }
+                expression_errors(expression, &scope, fns, errors);
+            }
+            expression_errors(&match_.default, vars, fns, errors);
+        }
+        Operand::If(ref condition, ref then, ref else_) => {
+            expression_errors(condition, vars, fns, errors);
+            expression_errors(then, vars, fns, errors);
+            expression_errors(else_, vars, fns, errors);
+        }
+        Operand::Not(ref operand) | Operand::Negate(ref operand) => {
+            operand_errors(operand, vars, fns, errors)
+        }
+        Operand::Let(ref bindings, ref body) => {
+            let mut scope = vars.clone();
+            for &(ref name, ref value) in bindings {
+                expression_errors(value, &scope, fns, errors);
+                scope.insert(name.clone());
+            }
+            expression_errors(body, &scope, fns, errors);
+        }
+    }
+}
+
+fn matcher_errors(
+    matcher: &Matcher,
+    vars: &HashSet<Name>,
+    fns: &HashMap<Name, usize>,
+    errors: &mut Vec<Error>,
+) {
+    match *matcher {
+        Matcher::Value(ref value) => expression_errors(value, vars, fns, errors),
+        Matcher::Range { ref start, ref end, .. } => {
+            expression_errors(start, vars, fns, errors);
+            expression_errors(end, vars, fns, errors);
+        }
+        Matcher::Alternatives(ref matchers) | Matcher::Tuple(ref matchers) => {
+            for matcher in matchers {
+                matcher_errors(matcher, vars, fns, errors);
+            }
+        }
+        Matcher::Binding(_, ref matcher) => matcher_errors(matcher, vars, fns, errors),
+        Matcher::Wildcard => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::parser::parse;
+    use quickcheck::{QuickCheck, StdGen};
+    use rand::thread_rng;
+
+    fn errors_of(program: &str) -> Vec<Error> {
+        errors(&parse(program.as_bytes()).unwrap())
+    }
+
+    #[test]
+    fn finds_errors() {
+        assert_eq!(
+            errors_of(
+                "inputs a; f(x, y, x) = x + a; g(n) = h(n) + g(n, 1); b = f(1, 2) + c; \
+                 outputs b, d;"
+            ),
+            vec![
+                Error::DuplicateParameter {
+                    function: Name::new("f"),
+                    name: Name::new("x"),
+                },
+                Error::UnknownVariable(Name::new("a")),
+                Error::UnknownFunction(Name::new("h")),
+                Error::IncorrectArgumentCount {
+                    name: Name::new("g"),
+                    params_count: 1,
+                    provided_count: 2,
+                },
+                Error::IncorrectArgumentCount {
+                    name: Name::new("f"),
+                    params_count: 3,
+                    provided_count: 2,
+                },
+                Error::UnknownVariable(Name::new("c")),
+                Error::UnassignedOutput(Name::new("d")),
+            ]
+        );
+    }
+
+    #[test]
+    fn follows_scopes() {
+        assert_eq!(
+            errors_of(
+                "inputs a; b = let c = a, d = c in \
+                 match (c, d) { (let e, 1) if e > 0 => e, _ => d }; \
+                 f(n) = m where m = match n { k @ 1..n => k + n, _ => f(n - 1) }; \
+                 g(n) = n; outputs a, b;"
+            ),
+            vec![]
+        );
+        assert_eq!(
+            errors_of("inputs; x = match 1 { let e => e, _ => e }; f() = g(); g() = 1; outputs;"),
+            vec![
+                Error::UnknownVariable(Name::new("e")),
+                Error::UnknownFunction(Name::new("g")),
+            ]
+        );
+    }
+
+    fn generated_programs_pass_prop(program: Program) -> bool {
+        errors(&program).is_empty()
+    }
+
+    #[test]
+    fn generated_programs_pass() {
+        for size in 1..11 {
+            let mut qc = QuickCheck::new().gen(StdGen::new(thread_rng(), size));
+            qc.quickcheck(generated_programs_pass_prop as fn(Program) -> bool);
+        }
+    }
+}
@@ -39,6 +39,7 @@ impl<'a> ExpressionSynthesiser<'a> {
             &Expression::Operation(operator, ref lhs, ref rhs) => {
                 self.synthesise_operation(operator, lhs, rhs)
             }
+            &Expression::Spanned(_, ref expression) => self.synthesise(expression),
         }
     }
 
@@ -51,15 +52,196 @@ impl<'a> ExpressionSynthesiser<'a> {
         let module = self.module;
         let builder = self.builder;
         let lhs = self.synthesise(lhs);
+        match operator {
+            Operator::Or | Operator::And => return self.synthesise_logical(operator, lhs, rhs),
+            _ => {}
+        }
         let rhs = self.synthesise(rhs);
         match operator {
+            Operator::Or | Operator::And => unreachable!(),
+            Operator::Equal => self.synthesise_comparison(LLVMIntPredicate::LLVMIntEQ, lhs, rhs),
+            Operator::NotEqual => self.synthesise_comparison(LLVMIntPredicate::LLVMIntNE, lhs, rhs),
+            Operator::LessThan => {
+                self.synthesise_comparison(LLVMIntPredicate::LLVMIntSLT, lhs, rhs)
+            }
+            Operator::LessThanOrEqual => {
+                self.synthesise_comparison(LLVMIntPredicate::LLVMIntSLE, lhs, rhs)
+            }
+            Operator::GreaterThan => {
+                self.synthesise_comparison(LLVMIntPredicate::LLVMIntSGT, lhs, rhs)
+            }
+            Operator::GreaterThanOrEqual => {
+                self.synthesise_comparison(LLVMIntPredicate::LLVMIntSGE, lhs, rhs)
+            }
             Operator::Subtract => saturating_sub(module, builder, lhs, rhs, llvm_name("")),
             Operator::Add => saturating_add(module, builder, lhs, rhs, llvm_name("")),
             Operator::Divide => saturating_div(module, builder, lhs, rhs, llvm_name("")),
+            Operator::Remainder => saturating_rem(module, builder, lhs, rhs, llvm_name("")),
+            Operator::Modulo => saturating_mod(module, builder, lhs, rhs, llvm_name("")),
             Operator::Multiply => saturating_mul(module, builder, lhs, rhs, llvm_name("")),
+            Operator::Power => saturating_pow(module, builder, lhs, rhs, llvm_name("")),
+            Operator::BitOr => {
+                let name = llvm_name("");
+                assert_not_nil(LLVMBuildOr(builder, lhs, rhs, name.as_ptr()))
+            }
+            Operator::BitXor => {
+                let name = llvm_name("");
+                assert_not_nil(LLVMBuildXor(builder, lhs, rhs, name.as_ptr()))
+            }
+            Operator::BitAnd => {
+                let name = llvm_name("");
+                assert_not_nil(LLVMBuildAnd(builder, lhs, rhs, name.as_ptr()))
+            }
+            Operator::ShiftLeft | Operator::ShiftRight => self.synthesise_shift(operator, lhs, rhs),
+        }
+    }
+
+    // LLVM leaves shifts by `64` or more undefined, so out of range counts are handled the
+    // same way as the interpreter: `<<` gives zero and `>>` gives copies of the sign bit.
+    unsafe fn synthesise_shift(
+        &self,
+        operator: Operator,
+        lhs: LLVMValueRef,
+        rhs: LLVMValueRef,
+    ) -> LLVMValueRef {
+        let i64_type = LLVMInt64TypeInContext(self.ctx);
+
+        let name = llvm_name("shift_in_range");
+        let in_range = assert_not_nil(LLVMBuildICmp(
+            self.builder,
+            LLVMIntPredicate::LLVMIntULT,
+            rhs,
+            LLVMConstInt(i64_type, 64, 0),
+            name.as_ptr(),
+        ));
+        match operator {
+            Operator::ShiftLeft => {
+                let name = llvm_name("shifted");
+                let shifted = assert_not_nil(LLVMBuildShl(self.builder, lhs, rhs, name.as_ptr()));
+                let name = llvm_name("");
+                assert_not_nil(LLVMBuildSelect(
+                    self.builder,
+                    in_range,
+                    shifted,
+                    LLVMConstInt(i64_type, 0, 0),
+                    name.as_ptr(),
+                ))
+            }
+            Operator::ShiftRight => {
+                let name = llvm_name("shift_count");
+                let count = assert_not_nil(LLVMBuildSelect(
+                    self.builder,
+                    in_range,
+                    rhs,
+                    LLVMConstInt(i64_type, 63, 0),
+                    name.as_ptr(),
+                ));
+                let name = llvm_name("");
+                assert_not_nil(LLVMBuildAShr(self.builder, lhs, count, name.as_ptr()))
+            }
+            _ => unreachable!(),
         }
     }
 
+    // Only evaluates `rhs` if `lhs` does not already determine the result.
+    unsafe fn synthesise_logical(
+        &self,
+        operator: Operator,
+        lhs: LLVMValueRef,
+        rhs: &Expression,
+    ) -> LLVMValueRef {
+        let i64_type = LLVMInt64TypeInContext(self.ctx);
+
+        let name = llvm_name("logical_dest");
+        let dest = allocate(self.builder, i64_type, name);
+
+        let name = llvm_name("logical_rhs");
+        let rhs_block = assert_not_nil(LLVMAppendBasicBlockInContext(
+            self.ctx,
+            self.function,
+            name.as_ptr(),
+        ));
+        let name = llvm_name("logical_short_circuit");
+        let short_circuit_block = assert_not_nil(LLVMAppendBasicBlockInContext(
+            self.ctx,
+            self.function,
+            name.as_ptr(),
+        ));
+        let name = llvm_name("logical_final");
+        let final_block = assert_not_nil(LLVMAppendBasicBlockInContext(
+            self.ctx,
+            self.function,
+            name.as_ptr(),
+        ));
+
+        let cmp_name = llvm_name("lhs_cmp");
+        let lhs_cmp = assert_not_nil(LLVMBuildICmp(
+            self.builder,
+            LLVMIntPredicate::LLVMIntNE,
+            lhs,
+            LLVMConstInt(i64_type, 0, 0),
+            cmp_name.as_ptr(),
+        ));
+        let short_circuit_value = match operator {
+            Operator::Or => {
+                assert_not_nil(LLVMBuildCondBr(
+                    self.builder,
+                    lhs_cmp,
+                    short_circuit_block,
+                    rhs_block,
+                ));
+                LLVMConstInt(i64_type, 1, 0)
+            }
+            Operator::And => {
+                assert_not_nil(LLVMBuildCondBr(
+                    self.builder,
+                    lhs_cmp,
+                    rhs_block,
+                    short_circuit_block,
+                ));
+                LLVMConstInt(i64_type, 0, 0)
+            }
+            _ => unreachable!(),
+        };
+
+        LLVMPositionBuilderAtEnd(self.builder, short_circuit_block);
+        assert_not_nil(LLVMBuildStore(self.builder, short_circuit_value, dest));
+        assert_not_nil(LLVMBuildBr(self.builder, final_block));
+
+        LLVMPositionBuilderAtEnd(self.builder, rhs_block);
+        let rhs = self.synthesise(rhs);
+        let rhs_value = self.synthesise_comparison(
+            LLVMIntPredicate::LLVMIntNE,
+            rhs,
+            LLVMConstInt(i64_type, 0, 0),
+        );
+        assert_not_nil(LLVMBuildStore(self.builder, rhs_value, dest));
+        assert_not_nil(LLVMBuildBr(self.builder, final_block));
+
+        LLVMPositionBuilderAtEnd(self.builder, final_block);
+        let name = llvm_name("logical");
+        assert_not_nil(LLVMBuildLoad(self.builder, dest, name.as_ptr()))
+    }
+
+    unsafe fn synthesise_comparison(
+        &self,
+        predicate: LLVMIntPredicate,
+        lhs: LLVMValueRef,
+        rhs: LLVMValueRef,
+    ) -> LLVMValueRef {
+        let i64_type = LLVMInt64TypeInContext(self.ctx);
+        let cmp_name = llvm_name("cmp");
+        let cmp = assert_not_nil(LLVMBuildICmp(
+            self.builder,
+            predicate,
+            lhs,
+            rhs,
+            cmp_name.as_ptr(),
+        ));
+        let name = llvm_name("cmp_zext");
+        assert_not_nil(LLVMBuildZExt(self.builder, cmp, i64_type, name.as_ptr()))
+    }
+
     unsafe fn synthesise_operand(&self, operand: &Operand) -> LLVMValueRef {
         let i64_type = LLVMInt64TypeInContext(self.ctx);
         let i64_ptr_type = LLVMPointerType(i64_type, 0);
@@ -88,14 +270,112 @@ impl<'a> ExpressionSynthesiser<'a> {
                 self.module,
                 self.builder,
                 self.function,
-                self.synthesise(&match_.with),
+                &match_.with.iter().map(|e| self.synthesise(e)).collect::<Vec<_>>(),
                 &match_.clauses,
                 &match_.default,
                 self.vars,
                 self.fns,
             ),
+            &Operand::If(ref condition, ref then, ref else_) => {
+                self.synthesise_if(condition, then, else_)
+            }
+            &Operand::Not(ref operand) => {
+                let value = self.synthesise_operand(operand);
+                self.synthesise_comparison(
+                    LLVMIntPredicate::LLVMIntEQ,
+                    value,
+                    LLVMConstInt(i64_type, 0, 0),
+                )
+            }
+            &Operand::Negate(ref operand) => {
+                let value = self.synthesise_operand(operand);
+                saturating_sub(
+                    self.module,
+                    self.builder,
+                    LLVMConstInt(i64_type, 0, 0),
+                    value,
+                    llvm_name(""),
+                )
+            }
+            &Operand::Let(ref bindings, ref body) => self.synthesise_let(bindings, body),
         }
     }
+
+    // Bound values are plain SSA values, so bindings only extend the variables in scope.
+    unsafe fn synthesise_let(
+        &self,
+        bindings: &[(Name, Expression)],
+        body: &Expression,
+    ) -> LLVMValueRef {
+        let mut scope = self.vars.clone();
+        for &(ref name, ref value) in bindings {
+            let value = ExpressionSynthesiser {
+                vars: &scope,
+                ..*self
+            }.synthesise(value);
+            scope.insert(name.clone(), value);
+        }
+        ExpressionSynthesiser {
+            vars: &scope,
+            ..*self
+        }.synthesise(body)
+    }
+
+    unsafe fn synthesise_if(
+        &self,
+        condition: &Expression,
+        then: &Expression,
+        else_: &Expression,
+    ) -> LLVMValueRef {
+        let i64_type = LLVMInt64TypeInContext(self.ctx);
+
+        let name = llvm_name("if_dest");
+        let dest = allocate(self.builder, i64_type, name);
+
+        let name = llvm_name("if_then");
+        let then_block = assert_not_nil(LLVMAppendBasicBlockInContext(
+            self.ctx,
+            self.function,
+            name.as_ptr(),
+        ));
+        let name = llvm_name("if_else");
+        let else_block = assert_not_nil(LLVMAppendBasicBlockInContext(
+            self.ctx,
+            self.function,
+            name.as_ptr(),
+        ));
+        let name = llvm_name("if_final");
+        let final_block = assert_not_nil(LLVMAppendBasicBlockInContext(
+            self.ctx,
+            self.function,
+            name.as_ptr(),
+        ));
+
+        let condition = self.synthesise(condition);
+        let cmp_name = llvm_name("if_cmp");
+        let cmp = assert_not_nil(LLVMBuildICmp(
+            self.builder,
+            LLVMIntPredicate::LLVMIntNE,
+            condition,
+            LLVMConstInt(i64_type, 0, 0),
+            cmp_name.as_ptr(),
+        ));
+        assert_not_nil(LLVMBuildCondBr(self.builder, cmp, then_block, else_block));
+
+        LLVMPositionBuilderAtEnd(self.builder, then_block);
+        let then_value = self.synthesise(then);
+        assert_not_nil(LLVMBuildStore(self.builder, then_value, dest));
+        assert_not_nil(LLVMBuildBr(self.builder, final_block));
+
+        LLVMPositionBuilderAtEnd(self.builder, else_block);
+        let else_value = self.synthesise(else_);
+        assert_not_nil(LLVMBuildStore(self.builder, else_value, dest));
+        assert_not_nil(LLVMBuildBr(self.builder, final_block));
+
+        LLVMPositionBuilderAtEnd(self.builder, final_block);
+        let name = llvm_name("if");
+        assert_not_nil(LLVMBuildLoad(self.builder, dest, name.as_ptr()))
+    }
 }
 
 pub unsafe fn synthesise_match(
@@ -103,8 +383,8 @@ pub unsafe fn synthesise_match(
     module: LLVMModuleRef,
     builder: LLVMBuilderRef,
     function: LLVMValueRef,
-    with: LLVMValueRef,
-    matchers: &Vec<(Matcher, Expression)>,
+    with: &[LLVMValueRef],
+    matchers: &Vec<(Matcher, Option<Expression>, Expression)>,
     default: &Expression,
     vars: &HashMap<Name, LLVMValueRef>,
     functions: &HashMap<Name, LLVMValueRef>,
@@ -117,35 +397,76 @@ pub unsafe fn synthesise_match(
     let name = llvm_name("match_final");
     let final_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
 
-    for &(ref matcher, ref expression) in matchers {
+    for &(ref matcher, ref guard, ref expression) in matchers {
         let name = llvm_name("match_assignment");
         let assignment_block =
             assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
         let name = llvm_name("match_cmp");
         let cmp_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
 
-        match matcher {
-            &Matcher::Value(ref cmp_expression) => {
-                // Evaluate matcher expression.
-                let cmp_value = synthesise_expression(
+        let bindings = matcher.bindings();
+        let mut scope;
+        let clause_vars = if bindings.is_empty() {
+            vars
+        } else {
+            scope = vars.clone();
+            for (i, name) in bindings {
+                scope.insert(name, with[i]);
+            }
+            &scope
+        };
+
+        match guard {
+            &Some(ref guard) => {
+                let name = llvm_name("match_guard");
+                let guard_block =
+                    assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+                synthesise_matcher(
                     ctx,
                     module,
                     builder,
                     function,
-                    cmp_expression,
+                    with,
+                    matcher,
+                    guard_block,
+                    cmp_block,
                     vars,
                     functions,
                 );
-                let cmp_name = llvm_name("cmp");
+
+                // A false guard falls through to the next clause.
+                LLVMPositionBuilderAtEnd(builder, guard_block);
+                let guard_value = synthesise_expression(
+                    ctx,
+                    module,
+                    builder,
+                    function,
+                    guard,
+                    clause_vars,
+                    functions,
+                );
+                let cmp_name = llvm_name("guard_cmp");
                 let cmp = assert_not_nil(LLVMBuildICmp(
                     builder,
-                    LLVMIntPredicate::LLVMIntEQ,
-                    with,
-                    cmp_value,
+                    LLVMIntPredicate::LLVMIntNE,
+                    guard_value,
+                    LLVMConstInt(i64_type, 0, 0),
                     cmp_name.as_ptr(),
                 ));
                 assert_not_nil(LLVMBuildCondBr(builder, cmp, assignment_block, cmp_block));
             }
+            &None => synthesise_matcher(
+                ctx,
+                module,
+                builder,
+                function,
+                with,
+                matcher,
+                assignment_block,
+                cmp_block,
+                vars,
+                functions,
+            ),
         }
 
         LLVMPositionBuilderAtEnd(builder, assignment_block);
@@ -155,7 +476,7 @@ pub unsafe fn synthesise_match(
             builder,
             function,
             expression,
-            &vars,
+            clause_vars,
             &functions,
         );
         assert_not_nil(LLVMBuildStore(builder, value, dest));
@@ -173,3 +494,164 @@ pub unsafe fn synthesise_match(
     let name = llvm_name("match");
     assert_not_nil(LLVMBuildLoad(builder, dest, name.as_ptr()))
 }
+
+// Branches to `matched_block` if `values` are matched by `matcher`, else to `unmatched_block`.
+// Every matcher but a `Tuple` only looks at the first value.
+unsafe fn synthesise_matcher(
+    ctx: LLVMContextRef,
+    module: LLVMModuleRef,
+    builder: LLVMBuilderRef,
+    function: LLVMValueRef,
+    values: &[LLVMValueRef],
+    matcher: &Matcher,
+    matched_block: LLVMBasicBlockRef,
+    unmatched_block: LLVMBasicBlockRef,
+    vars: &HashMap<Name, LLVMValueRef>,
+    functions: &HashMap<Name, LLVMValueRef>,
+) {
+    let with = values[0];
+    match matcher {
+        &Matcher::Value(ref cmp_expression) => {
+            // Evaluate matcher expression.
+            let cmp_value = synthesise_expression(
+                ctx,
+                module,
+                builder,
+                function,
+                cmp_expression,
+                vars,
+                functions,
+            );
+            let cmp_name = llvm_name("cmp");
+            let cmp = assert_not_nil(LLVMBuildICmp(
+                builder,
+                LLVMIntPredicate::LLVMIntEQ,
+                with,
+                cmp_value,
+                cmp_name.as_ptr(),
+            ));
+            assert_not_nil(LLVMBuildCondBr(
+                builder,
+                cmp,
+                matched_block,
+                unmatched_block,
+            ));
+        }
+        &Matcher::Range {
+            ref start,
+            ref end,
+            inclusive,
+        } => {
+            let start_value =
+                synthesise_expression(ctx, module, builder, function, start, vars, functions);
+            let end_value =
+                synthesise_expression(ctx, module, builder, function, end, vars, functions);
+            let start_cmp_name = llvm_name("range_start_cmp");
+            let start_cmp = assert_not_nil(LLVMBuildICmp(
+                builder,
+                LLVMIntPredicate::LLVMIntSLE,
+                start_value,
+                with,
+                start_cmp_name.as_ptr(),
+            ));
+            let end_predicate = if inclusive {
+                LLVMIntPredicate::LLVMIntSLE
+            } else {
+                LLVMIntPredicate::LLVMIntSLT
+            };
+            let end_cmp_name = llvm_name("range_end_cmp");
+            let end_cmp = assert_not_nil(LLVMBuildICmp(
+                builder,
+                end_predicate,
+                with,
+                end_value,
+                end_cmp_name.as_ptr(),
+            ));
+            let cmp_name = llvm_name("cmp");
+            let cmp = assert_not_nil(LLVMBuildAnd(builder, start_cmp, end_cmp, cmp_name.as_ptr()));
+            assert_not_nil(LLVMBuildCondBr(
+                builder,
+                cmp,
+                matched_block,
+                unmatched_block,
+            ));
+        }
+        &Matcher::Alternatives(ref matchers) => {
+            // Later alternatives are only evaluated if earlier ones fail to match.
+            for (i, matcher) in matchers.iter().enumerate() {
+                if i + 1 == matchers.len() {
+                    synthesise_matcher(
+                        ctx,
+                        module,
+                        builder,
+                        function,
+                        values,
+                        matcher,
+                        matched_block,
+                        unmatched_block,
+                        vars,
+                        functions,
+                    );
+                } else {
+                    let name = llvm_name("match_alternative");
+                    let next_block =
+                        assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+                    synthesise_matcher(
+                        ctx,
+                        module,
+                        builder,
+                        function,
+                        values,
+                        matcher,
+                        matched_block,
+                        next_block,
+                        vars,
+                        functions,
+                    );
+                    LLVMPositionBuilderAtEnd(builder, next_block);
+                }
+            }
+        }
+        &Matcher::Wildcard => {
+            assert_not_nil(LLVMBuildBr(builder, matched_block));
+        }
+        &Matcher::Binding(_, ref matcher) => synthesise_matcher(
+            ctx,
+            module,
+            builder,
+            function,
+            values,
+            matcher,
+            matched_block,
+            unmatched_block,
+            vars,
+            functions,
+        ),
+        &Matcher::Tuple(ref matchers) => {
+            // Later elements are only evaluated if earlier ones match.
+            for (i, matcher) in matchers.iter().enumerate() {
+                let next_block = if i + 1 == matchers.len() {
+                    matched_block
+                } else {
+                    let name = llvm_name("match_element");
+                    assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()))
+                };
+                synthesise_matcher(
+                    ctx,
+                    module,
+                    builder,
+                    function,
+                    &values[i..],
+                    matcher,
+                    next_block,
+                    unmatched_block,
+                    vars,
+                    functions,
+                );
+                if next_block != matched_block {
+                    LLVMPositionBuilderAtEnd(builder, next_block);
+                }
+            }
+        }
+    }
+}
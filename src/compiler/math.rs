@@ -426,6 +426,470 @@ pub unsafe fn define_saturating_div(
     function
 }
 
+// Remainders by zero return the numerator unchanged, and `i64::MIN % -1` is 0 rather
+// than the undefined behaviour of `srem`.
+pub unsafe fn define_saturating_rem(
+    ctx: LLVMContextRef,
+    module: LLVMModuleRef,
+    builder: LLVMBuilderRef,
+) -> LLVMValueRef {
+    let i64_type = LLVMInt64TypeInContext(ctx);
+
+    let fn_name = llvm_name("saturating_rem");
+    let param_types = vec![
+        (Name::new("numerator"), i64_type),
+        (Name::new("denominator"), i64_type),
+    ];
+    let (function, param_values) = function_definition(module, fn_name, param_types, i64_type);
+    let numerator = assert_not_nil(param_values[&Name::new("numerator")]);
+    let denominator = assert_not_nil(param_values[&Name::new("denominator")]);
+
+    let name = llvm_name("entry");
+    let entry_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("ok");
+    let ok_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("saturate");
+    let saturate_block =
+        assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+
+    LLVMPositionBuilderAtEnd(builder, entry_block);
+    let zero_cmp_name = llvm_name("denominator_is_zero");
+    let zero_cmp = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntEQ,
+        denominator,
+        LLVMConstInt(i64_type, 0, 0),
+        zero_cmp_name.as_ptr(),
+    ));
+    let rhs_is_minus1_cmp_name = llvm_name("denominator_is_minus_1");
+    let rhs_is_minus1_cmp = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntEQ,
+        denominator,
+        LLVMConstInt(i64_type, (-1i64) as u64, 0),
+        rhs_is_minus1_cmp_name.as_ptr(),
+    ));
+    let cmp_name = llvm_name("overflowing");
+    let cmp = assert_not_nil(LLVMBuildOr(
+        builder,
+        zero_cmp,
+        rhs_is_minus1_cmp,
+        cmp_name.as_ptr(),
+    ));
+    assert_not_nil(LLVMBuildCondBr(builder, cmp, saturate_block, ok_block));
+
+    LLVMPositionBuilderAtEnd(builder, ok_block);
+    let name = llvm_name("rem");
+    let srem = assert_not_nil(LLVMBuildSRem(
+        builder,
+        numerator,
+        denominator,
+        name.as_ptr(),
+    ));
+    function_return(builder, srem);
+
+    // Anything divided by -1 leaves no remainder.
+    LLVMPositionBuilderAtEnd(builder, saturate_block);
+    let name = llvm_name("saturated");
+    let saturated = assert_not_nil(LLVMBuildSelect(
+        builder,
+        zero_cmp,
+        numerator,
+        LLVMConstInt(i64_type, 0, 0),
+        name.as_ptr(),
+    ));
+    function_return(builder, saturated);
+
+    function
+}
+
+// Euclidean remainder, which is never negative unless the denominator is zero, when like
+// `saturating_rem` it leaves the numerator unchanged. Shares the saturation of `saturating_rem`.
+pub unsafe fn define_saturating_mod(
+    ctx: LLVMContextRef,
+    module: LLVMModuleRef,
+    builder: LLVMBuilderRef,
+) -> LLVMValueRef {
+    let i64_type = LLVMInt64TypeInContext(ctx);
+
+    let fn_name = llvm_name("saturating_mod");
+    let param_types = vec![
+        (Name::new("numerator"), i64_type),
+        (Name::new("denominator"), i64_type),
+    ];
+    let (function, param_values) = function_definition(module, fn_name, param_types, i64_type);
+    let numerator = assert_not_nil(param_values[&Name::new("numerator")]);
+    let denominator = assert_not_nil(param_values[&Name::new("denominator")]);
+
+    let name = llvm_name("entry");
+    let entry_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("ok");
+    let ok_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("adjust");
+    let adjust_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+
+    LLVMPositionBuilderAtEnd(builder, entry_block);
+    let rem = saturating_rem(
+        module,
+        builder,
+        numerator,
+        denominator,
+        llvm_name("rem"),
+    );
+    let rem_neg_cmp_name = llvm_name("rem_is_negative");
+    let rem_neg = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntSLT,
+        rem,
+        LLVMConstInt(i64_type, 0, 0),
+        rem_neg_cmp_name.as_ptr(),
+    ));
+    assert_not_nil(LLVMBuildCondBr(builder, rem_neg, adjust_block, ok_block));
+
+    LLVMPositionBuilderAtEnd(builder, ok_block);
+    function_return(builder, rem);
+
+    // Shifting a negative remainder by the denominator's magnitude cannot overflow.
+    LLVMPositionBuilderAtEnd(builder, adjust_block);
+    let rhs_neg_cmp_name = llvm_name("denominator_is_negative");
+    let rhs_neg = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntSLT,
+        denominator,
+        LLVMConstInt(i64_type, 0, 0),
+        rhs_neg_cmp_name.as_ptr(),
+    ));
+    let name = llvm_name("rem_sub");
+    let rem_sub = assert_not_nil(LLVMBuildSub(builder, rem, denominator, name.as_ptr()));
+    let name = llvm_name("rem_add");
+    let rem_add = assert_not_nil(LLVMBuildAdd(builder, rem, denominator, name.as_ptr()));
+    let name = llvm_name("mod");
+    let modulo = assert_not_nil(LLVMBuildSelect(
+        builder,
+        rhs_neg,
+        rem_sub,
+        rem_add,
+        name.as_ptr(),
+    ));
+    function_return(builder, modulo);
+
+    function
+}
+
+// Square-and-multiply, saturating on overflow. Negative exponents truncate
+// `1 / base ^ -exponent` towards zero, with `0 ^ -n` saturating like division by zero.
+pub unsafe fn define_saturating_pow(
+    ctx: LLVMContextRef,
+    module: LLVMModuleRef,
+    builder: LLVMBuilderRef,
+) -> LLVMValueRef {
+    let i64_type = LLVMInt64TypeInContext(ctx);
+
+    let fn_name = llvm_name("llvm.smul.with.overflow.i64");
+    let smul_overflow = assert_not_nil(LLVMGetNamedFunction(module, fn_name.as_ptr()));
+
+    let fn_name = llvm_name("saturating_pow");
+    let param_types = vec![
+        (Name::new("base"), i64_type),
+        (Name::new("exponent"), i64_type),
+    ];
+    let (function, param_values) = function_definition(module, fn_name, param_types, i64_type);
+    let base = assert_not_nil(param_values[&Name::new("base")]);
+    let exponent = assert_not_nil(param_values[&Name::new("exponent")]);
+
+    let name = llvm_name("entry");
+    let entry_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("negative_exponent");
+    let negative_exponent_block =
+        assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("loop");
+    let loop_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("body");
+    let body_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("multiply");
+    let multiply_block =
+        assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("multiply_ok");
+    let multiply_ok_block =
+        assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("shift");
+    let shift_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("square");
+    let square_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("square_ok");
+    let square_ok_block =
+        assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("done");
+    let done_block = assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+    let name = llvm_name("saturate");
+    let saturate_block =
+        assert_not_nil(LLVMAppendBasicBlockInContext(ctx, function, name.as_ptr()));
+
+    LLVMPositionBuilderAtEnd(builder, entry_block);
+    let result_ptr = allocate(builder, i64_type, llvm_name("result_ptr"));
+    let square_ptr = allocate(builder, i64_type, llvm_name("square_ptr"));
+    let exponent_ptr = allocate(builder, i64_type, llvm_name("exponent_ptr"));
+    assert_not_nil(LLVMBuildStore(
+        builder,
+        LLVMConstInt(i64_type, 1, 0),
+        result_ptr,
+    ));
+    assert_not_nil(LLVMBuildStore(builder, base, square_ptr));
+    assert_not_nil(LLVMBuildStore(builder, exponent, exponent_ptr));
+    let name = llvm_name("exponent_odd_bit");
+    let exponent_odd_bit = assert_not_nil(LLVMBuildAnd(
+        builder,
+        exponent,
+        LLVMConstInt(i64_type, 1, 0),
+        name.as_ptr(),
+    ));
+    let cmp_name = llvm_name("exponent_is_odd");
+    let exponent_is_odd = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntNE,
+        exponent_odd_bit,
+        LLVMConstInt(i64_type, 0, 0),
+        cmp_name.as_ptr(),
+    ));
+    let cmp_name = llvm_name("exponent_is_negative");
+    let exponent_is_negative = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntSLT,
+        exponent,
+        LLVMConstInt(i64_type, 0, 0),
+        cmp_name.as_ptr(),
+    ));
+    assert_not_nil(LLVMBuildCondBr(
+        builder,
+        exponent_is_negative,
+        negative_exponent_block,
+        loop_block,
+    ));
+
+    LLVMPositionBuilderAtEnd(builder, negative_exponent_block);
+    let cmp_name = llvm_name("base_is_zero");
+    let base_is_zero = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntEQ,
+        base,
+        LLVMConstInt(i64_type, 0, 0),
+        cmp_name.as_ptr(),
+    ));
+    let cmp_name = llvm_name("base_is_one");
+    let base_is_one = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntEQ,
+        base,
+        LLVMConstInt(i64_type, 1, 0),
+        cmp_name.as_ptr(),
+    ));
+    let cmp_name = llvm_name("base_is_minus_1");
+    let base_is_minus1 = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntEQ,
+        base,
+        LLVMConstInt(i64_type, (-1i64) as u64, 0),
+        cmp_name.as_ptr(),
+    ));
+    let name = llvm_name("minus_1_pow");
+    let minus1_pow = assert_not_nil(LLVMBuildSelect(
+        builder,
+        exponent_is_odd,
+        LLVMConstInt(i64_type, (-1i64) as u64, 0),
+        LLVMConstInt(i64_type, 1, 0),
+        name.as_ptr(),
+    ));
+    let name = llvm_name("zero_pow");
+    let zero_pow = assert_not_nil(LLVMBuildSelect(
+        builder,
+        base_is_zero,
+        LLVMConstInt(i64_type, i64::max_value() as u64, 0),
+        LLVMConstInt(i64_type, 0, 0),
+        name.as_ptr(),
+    ));
+    let name = llvm_name("one_pow");
+    let one_pow = assert_not_nil(LLVMBuildSelect(
+        builder,
+        base_is_one,
+        LLVMConstInt(i64_type, 1, 0),
+        zero_pow,
+        name.as_ptr(),
+    ));
+    let name = llvm_name("pow");
+    let negative_pow = assert_not_nil(LLVMBuildSelect(
+        builder,
+        base_is_minus1,
+        minus1_pow,
+        one_pow,
+        name.as_ptr(),
+    ));
+    function_return(builder, negative_pow);
+
+    LLVMPositionBuilderAtEnd(builder, loop_block);
+    let loop_exponent = load(builder, exponent_ptr, llvm_name("loop_exponent"));
+    let cmp_name = llvm_name("exponent_remaining");
+    let exponent_remaining = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntSGT,
+        loop_exponent,
+        LLVMConstInt(i64_type, 0, 0),
+        cmp_name.as_ptr(),
+    ));
+    assert_not_nil(LLVMBuildCondBr(
+        builder,
+        exponent_remaining,
+        body_block,
+        done_block,
+    ));
+
+    LLVMPositionBuilderAtEnd(builder, body_block);
+    let name = llvm_name("bit");
+    let bit = assert_not_nil(LLVMBuildAnd(
+        builder,
+        loop_exponent,
+        LLVMConstInt(i64_type, 1, 0),
+        name.as_ptr(),
+    ));
+    let cmp_name = llvm_name("bit_is_set");
+    let bit_is_set = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntNE,
+        bit,
+        LLVMConstInt(i64_type, 0, 0),
+        cmp_name.as_ptr(),
+    ));
+    assert_not_nil(LLVMBuildCondBr(
+        builder,
+        bit_is_set,
+        multiply_block,
+        shift_block,
+    ));
+
+    LLVMPositionBuilderAtEnd(builder, multiply_block);
+    let result = load(builder, result_ptr, llvm_name("result"));
+    let square = load(builder, square_ptr, llvm_name("square"));
+    let product = function_call(
+        builder,
+        smul_overflow,
+        &mut [result, square],
+        llvm_name("tmp_smul_overflow"),
+    );
+    let value_name = llvm_name("value");
+    let value = assert_not_nil(LLVMBuildExtractValue(
+        builder,
+        product,
+        0,
+        value_name.as_ptr(),
+    ));
+    let overflowed_name = llvm_name("overflowed");
+    let overflowed = assert_not_nil(LLVMBuildExtractValue(
+        builder,
+        product,
+        1,
+        overflowed_name.as_ptr(),
+    ));
+    assert_not_nil(LLVMBuildCondBr(
+        builder,
+        overflowed,
+        saturate_block,
+        multiply_ok_block,
+    ));
+
+    LLVMPositionBuilderAtEnd(builder, multiply_ok_block);
+    assert_not_nil(LLVMBuildStore(builder, value, result_ptr));
+    assert_not_nil(LLVMBuildBr(builder, shift_block));
+
+    LLVMPositionBuilderAtEnd(builder, shift_block);
+    let name = llvm_name("shifted_exponent");
+    let shifted_exponent = assert_not_nil(LLVMBuildLShr(
+        builder,
+        loop_exponent,
+        LLVMConstInt(i64_type, 1, 0),
+        name.as_ptr(),
+    ));
+    assert_not_nil(LLVMBuildStore(builder, shifted_exponent, exponent_ptr));
+    let cmp_name = llvm_name("squaring_needed");
+    let squaring_needed = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntSGT,
+        shifted_exponent,
+        LLVMConstInt(i64_type, 0, 0),
+        cmp_name.as_ptr(),
+    ));
+    assert_not_nil(LLVMBuildCondBr(
+        builder,
+        squaring_needed,
+        square_block,
+        loop_block,
+    ));
+
+    // Once squaring overflows, any further multiplication into the result would too.
+    LLVMPositionBuilderAtEnd(builder, square_block);
+    let square = load(builder, square_ptr, llvm_name("square"));
+    let product = function_call(
+        builder,
+        smul_overflow,
+        &mut [square, square],
+        llvm_name("tmp_smul_overflow"),
+    );
+    let value_name = llvm_name("value");
+    let value = assert_not_nil(LLVMBuildExtractValue(
+        builder,
+        product,
+        0,
+        value_name.as_ptr(),
+    ));
+    let overflowed_name = llvm_name("overflowed");
+    let overflowed = assert_not_nil(LLVMBuildExtractValue(
+        builder,
+        product,
+        1,
+        overflowed_name.as_ptr(),
+    ));
+    assert_not_nil(LLVMBuildCondBr(
+        builder,
+        overflowed,
+        saturate_block,
+        square_ok_block,
+    ));
+
+    LLVMPositionBuilderAtEnd(builder, square_ok_block);
+    assert_not_nil(LLVMBuildStore(builder, value, square_ptr));
+    assert_not_nil(LLVMBuildBr(builder, loop_block));
+
+    LLVMPositionBuilderAtEnd(builder, done_block);
+    let result = load(builder, result_ptr, llvm_name("result"));
+    function_return(builder, result);
+
+    LLVMPositionBuilderAtEnd(builder, saturate_block);
+    let cmp_name = llvm_name("base_is_negative");
+    let base_is_negative = assert_not_nil(LLVMBuildICmp(
+        builder,
+        LLVMIntPredicate::LLVMIntSLT,
+        base,
+        LLVMConstInt(i64_type, 0, 0),
+        cmp_name.as_ptr(),
+    ));
+    let cmp_name = llvm_name("saturating_to_min");
+    let saturate_cmp = assert_not_nil(LLVMBuildAnd(
+        builder,
+        base_is_negative,
+        exponent_is_odd,
+        cmp_name.as_ptr(),
+    ));
+    let name = llvm_name("saturated");
+    let saturated = assert_not_nil(LLVMBuildSelect(
+        builder,
+        saturate_cmp,
+        LLVMConstInt(i64_type, i64::min_value() as u64, 0),
+        LLVMConstInt(i64_type, i64::max_value() as u64, 0),
+        name.as_ptr(),
+    ));
+    function_return(builder, saturated);
+
+    function
+}
+
 pub unsafe fn saturating_add(
     module: LLVMModuleRef,
     builder: LLVMBuilderRef,
@@ -481,3 +945,45 @@ pub unsafe fn saturating_div(
     let args = &mut [numerator, denominator];
     function_call(builder, saturating_div_fn, args, name)
 }
+
+pub unsafe fn saturating_rem(
+    module: LLVMModuleRef,
+    builder: LLVMBuilderRef,
+    numerator: LLVMValueRef,
+    denominator: LLVMValueRef,
+    name: CString,
+) -> LLVMValueRef {
+    let saturating_rem_name = llvm_name("saturating_rem");
+    let saturating_rem_fn =
+        assert_not_nil(LLVMGetNamedFunction(module, saturating_rem_name.as_ptr()));
+    let args = &mut [numerator, denominator];
+    function_call(builder, saturating_rem_fn, args, name)
+}
+
+pub unsafe fn saturating_mod(
+    module: LLVMModuleRef,
+    builder: LLVMBuilderRef,
+    numerator: LLVMValueRef,
+    denominator: LLVMValueRef,
+    name: CString,
+) -> LLVMValueRef {
+    let saturating_mod_name = llvm_name("saturating_mod");
+    let saturating_mod_fn =
+        assert_not_nil(LLVMGetNamedFunction(module, saturating_mod_name.as_ptr()));
+    let args = &mut [numerator, denominator];
+    function_call(builder, saturating_mod_fn, args, name)
+}
+
+pub unsafe fn saturating_pow(
+    module: LLVMModuleRef,
+    builder: LLVMBuilderRef,
+    base: LLVMValueRef,
+    exponent: LLVMValueRef,
+    name: CString,
+) -> LLVMValueRef {
+    let saturating_pow_name = llvm_name("saturating_pow");
+    let saturating_pow_fn =
+        assert_not_nil(LLVMGetNamedFunction(module, saturating_pow_name.as_ptr()));
+    let args = &mut [base, exponent];
+    function_call(builder, saturating_pow_fn, args, name)
+}
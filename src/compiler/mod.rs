@@ -24,6 +24,7 @@ use std::process::Command;
 use std::slice;
 use std::fs::File;
 use std::io::prelude::*;
+use std::env;
 use std::path::{Path, PathBuf};
 use std::iter;
 
@@ -34,10 +35,70 @@ pub enum Emit {
     Binary(PathBuf),
 }
 
+/// Why a checked program couldn't be compiled.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
-    Unknown,
-    UnassignedOutput(Name),
+    /// LLVM couldn't parse the IR generated for the program, with its message saying why.
+    InvalidIr(String),
+    /// LLVM has no target for the machine being compiled on.
+    TargetLookup { triple: String, message: String },
+    /// LLVM couldn't make an object file from the IR.
+    Emit(String),
+    /// `cc`, used for linking, couldn't be run at all.
+    LinkerUnavailable(String),
+    /// `cc` failed, exiting with `status` unless it was killed by a signal.
+    Linker { status: Option<i32>, stderr: String },
+    /// Writing to `path` failed.
+    Io { path: PathBuf, message: String },
+}
+
+impl Error {
+    /// Where in the source the error is, if it is about anywhere in particular.
+    pub fn span(&self) -> Option<Span> {
+        None
+    }
+
+    fn io(path: &Path, error: &::std::io::Error) -> Error {
+        Error::Io {
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::InvalidIr(ref message) => {
+                write!(f, "LLVM couldn't parse the generated IR: {}", message)
+            }
+            Error::TargetLookup {
+                ref triple,
+                ref message,
+            } => write!(f, "no LLVM target for `{}`: {}", triple, message),
+            Error::Emit(ref message) => write!(f, "LLVM couldn't emit an object file: {}", message),
+            Error::LinkerUnavailable(ref message) => {
+                write!(f, "couldn't run `cc` to link the program: {}", message)
+            }
+            Error::Linker {
+                status,
+                ref stderr,
+            } => {
+                match status {
+                    Some(code) => write!(f, "linking with `cc` failed with exit status {}", code)?,
+                    None => write!(f, "linking with `cc` was interrupted by a signal")?,
+                }
+                if !stderr.trim().is_empty() {
+                    write!(f, ":\n{}", stderr.trim_right())?;
+                }
+                Ok(())
+            }
+            Error::Io {
+                ref path,
+                ref message,
+            } => write!(f, "couldn't write `{}`: {}", path.display(), message),
+        }
+    }
 }
 
 pub unsafe fn compile(program: &Program, emit: Emit) -> Result<String, Error> {
@@ -51,7 +112,7 @@ pub unsafe fn compile(program: &Program, emit: Emit) -> Result<String, Error> {
         }
         Emit::Binary(pathbuf) => {
             let ir = synthesise(program, None)?;
-            let tempfile = NamedTempFile::new().unwrap();
+            let tempfile = NamedTempFile::new().map_err(|e| Error::io(&env::temp_dir(), &e))?;
             objectify(&ir, tempfile.path())?;
             link(tempfile.path(), pathbuf.as_path())?;
             drop(tempfile);
@@ -73,6 +134,9 @@ unsafe fn synthesise(program: &Program, ir_path: Option<&Path>) -> Result<String
     define_saturating_sub(ctx, module, builder);
     define_saturating_mul(ctx, module, builder);
     define_saturating_div(ctx, module, builder);
+    define_saturating_rem(ctx, module, builder);
+    define_saturating_mod(ctx, module, builder);
+    define_saturating_pow(ctx, module, builder);
 
     //let input_function = define_input(ctx, module, builder, program.inputs.clone());
     //let output_function = define_output(ctx, module, builder, program.outputs.clone());
@@ -82,13 +146,13 @@ unsafe fn synthesise(program: &Program, ir_path: Option<&Path>) -> Result<String
     let mut assign_set = HashSet::new();
     for statement in &program.statements.0 {
         match statement {
-            &Statement::FnDefinition(ref name, ref param_names, ref expr) => {
+            &Statement::FnDefinition(ref name, ref param_names, ref expr, ref bindings, _) => {
                 let params = param_names
                     .iter()
                     .cloned()
                     .zip(iter::repeat(i64_type))
                     .collect();
-                let (function, args) =
+                let (function, mut args) =
                     function_definition(module, into_llvm_name(name.clone()), params, i64_type);
                 functions.insert(name.clone(), function);
                 let block_name = llvm_name("entry");
@@ -98,11 +162,23 @@ unsafe fn synthesise(program: &Program, ir_path: Option<&Path>) -> Result<String
                     block_name.as_ptr(),
                 ));
                 LLVMPositionBuilderAtEnd(builder, block);
+                for &(ref name, ref value) in bindings {
+                    let value = synthesise_expression(
+                        ctx,
+                        module,
+                        builder,
+                        function,
+                        value,
+                        &args,
+                        &functions,
+                    );
+                    args.insert(name.clone(), value);
+                }
                 let value =
                     synthesise_expression(ctx, module, builder, function, expr, &args, &functions);
                 LLVMBuildRet(builder, value);
             }
-            &Statement::VarAssignment(ref name, ref expression) => {
+            &Statement::VarAssignment(ref name, ref expression, _) => {
                 assigns.push((name.clone(), expression.clone(), functions.clone()));
                 assign_set.insert(name.clone());
             }
@@ -126,17 +202,24 @@ unsafe fn synthesise(program: &Program, ir_path: Option<&Path>) -> Result<String
     LLVMContextDispose(ctx);
 
     if let Some(path) = ir_path {
-        let mut f = File::create(path).unwrap();
-        f.write_all(ir.as_bytes()).unwrap();
-        drop(f);
+        write_file(path, ir.as_bytes())?;
     }
-    // FIXME: Remove once refactor works
-    eprintln!("{}", ir);
     Ok(ir)
 }
 
 unsafe fn objectify(llvm_ir: &String, object_path: &Path) -> Result<(), Error> {
     let llvm_ctx = assert_not_nil(LLVMContextCreate());
+    let result = emit_object(llvm_ctx, llvm_ir, object_path);
+    // This also disposes of the module parsed into the context.
+    LLVMContextDispose(llvm_ctx);
+    result
+}
+
+unsafe fn emit_object(
+    llvm_ctx: LLVMContextRef,
+    llvm_ir: &String,
+    object_path: &Path,
+) -> Result<(), Error> {
     let llvm_ir_str = llvm_name(llvm_ir);
     let llvm_ir_buffer_name = llvm_name("llvm_ir_buffer");
     let llvm_ir_buffer = assert_not_nil(LLVMCreateMemoryBufferWithMemoryRange(
@@ -146,35 +229,38 @@ unsafe fn objectify(llvm_ir: &String, object_path: &Path) -> Result<(), Error> {
         0,
     ));
     let mut llvm_module = ptr::null_mut();
-    let mut errors = ptr::null_mut();
+    let mut message = ptr::null_mut();
     let return_code = llvm::ir_reader::LLVMParseIRInContext(
         llvm_ctx,
         llvm_ir_buffer,
         &mut llvm_module,
-        &mut errors,
+        &mut message,
     );
-    if errors != ptr::null_mut() {
-        println!("{}", CString::from_raw(errors).to_str().unwrap());
+    if return_code != 0 {
+        return Err(Error::InvalidIr(take_message(message)));
     }
-    assert_eq!(return_code, 0);
 
     llvm::target::LLVM_InitializeNativeTarget();
     llvm::target::LLVM_InitializeNativeAsmPrinter();
     llvm::target::LLVM_InitializeNativeAsmParser();
 
     let llvm_triple_s = assert_not_nil(llvm::target_machine::LLVMGetDefaultTargetTriple());
-    let llvm_triple = CStr::from_ptr(llvm_triple_s);
+    let llvm_triple = CString::from(CStr::from_ptr(llvm_triple_s));
+    LLVMDisposeMessage(llvm_triple_s);
     let mut llvm_target = ptr::null_mut();
-    assert_eq!(
-        llvm::target_machine::LLVMGetTargetFromTriple(
-            llvm_triple.as_ptr(),
-            &mut llvm_target,
-            ptr::null_mut(),
-        ),
-        0
-    );
+    if llvm::target_machine::LLVMGetTargetFromTriple(
+        llvm_triple.as_ptr(),
+        &mut llvm_target,
+        &mut message,
+    ) != 0
+    {
+        return Err(Error::TargetLookup {
+            triple: llvm_triple.to_string_lossy().into_owned(),
+            message: take_message(message),
+        });
+    }
 
-    let llvm_target_machine = assert_not_nil(llvm::target_machine::LLVMCreateTargetMachine(
+    let llvm_target_machine = llvm::target_machine::LLVMCreateTargetMachine(
         llvm_target,
         llvm_triple.as_ptr(),
         ptr::null(),
@@ -182,58 +268,89 @@ unsafe fn objectify(llvm_ir: &String, object_path: &Path) -> Result<(), Error> {
         llvm::target_machine::LLVMCodeGenOptLevel::LLVMCodeGenLevelNone,
         llvm::target_machine::LLVMRelocMode::LLVMRelocDefault,
         llvm::target_machine::LLVMCodeModel::LLVMCodeModelDefault,
-    ));
+    );
+    if llvm_target_machine.is_null() {
+        return Err(Error::Emit(format!(
+            "couldn't create a target machine for `{}`",
+            llvm_triple.to_string_lossy()
+        )));
+    }
 
     let mut llvm_mem_buf: LLVMMemoryBufferRef = ptr::null_mut();
-    assert_eq!(
-        llvm::target_machine::LLVMTargetMachineEmitToMemoryBuffer(
-            llvm_target_machine,
-            llvm_module,
-            llvm::target_machine::LLVMCodeGenFileType::LLVMObjectFile,
-            ptr::null_mut(),
-            &mut llvm_mem_buf,
-        ),
-        0
+    let return_code = llvm::target_machine::LLVMTargetMachineEmitToMemoryBuffer(
+        llvm_target_machine,
+        llvm_module,
+        llvm::target_machine::LLVMCodeGenFileType::LLVMObjectFile,
+        &mut message,
+        &mut llvm_mem_buf,
     );
+    llvm::target_machine::LLVMDisposeTargetMachine(llvm_target_machine);
+    if return_code != 0 {
+        return Err(Error::Emit(take_message(message)));
+    }
     let llvm_out = slice::from_raw_parts(
         LLVMGetBufferStart(llvm_mem_buf) as *const _,
         LLVMGetBufferSize(llvm_mem_buf) as usize,
     );
-
-    let mut object_file = File::create(object_path).unwrap();
-    object_file.write_all(llvm_out).unwrap();
-    drop(object_file);
-
-    libc::free(llvm_triple_s as *mut libc::c_void);
+    let result = write_file(object_path, llvm_out);
     LLVMDisposeMemoryBuffer(llvm_mem_buf);
-    llvm::target_machine::LLVMDisposeTargetMachine(llvm_target_machine);
-    LLVMDisposeModule(llvm_module);
-    LLVMContextDispose(llvm_ctx);
-
-    Ok(())
+    result
 }
 
 fn link(object_path: &Path, binary_path: &Path) -> Result<(), Error> {
-    assert!(
-        Command::new("cc")
-            .arg("-o")
-            .arg(binary_path)
-            .arg(object_path)
-            .spawn()
-            .expect("could not invoke cc for linking")
-            .wait()
-            .unwrap()
-            .success()
-    );
-    Ok(())
+    let output = Command::new("cc")
+        .arg("-o")
+        .arg(binary_path)
+        .arg(object_path)
+        .output()
+        .map_err(|e| Error::LinkerUnavailable(e.to_string()))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(Error::Linker {
+            status: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        })
+    }
+}
+
+fn write_file(path: &Path, contents: &[u8]) -> Result<(), Error> {
+    File::create(path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|e| Error::io(path, &e))
+}
+
+// Takes ownership of a message LLVM has given back, which it may not have.
+unsafe fn take_message(message: *mut libc::c_char) -> String {
+    if message.is_null() {
+        return "no reason given".to_string();
+    }
+    let s = CStr::from_ptr(message).to_string_lossy().trim_right().to_string();
+    LLVMDisposeMessage(message);
+    s
 }
 
 fn llvm_name(s: &str) -> CString {
     CString::new(s).unwrap()
 }
 
+// Symbols are kept to ASCII letters, digits and `_`. Other names, and those that could be
+// mistaken for mangled names, are mangled into `_U` followed by the name with each character
+// besides ASCII letters and digits escaped as `_{hex}_`.
 fn into_llvm_name(name: Name) -> CString {
-    llvm_name(name.0.as_str())
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || c == '_';
+    if name.0.chars().all(is_plain) && !name.0.starts_with("_U") {
+        return llvm_name(name.0.as_str());
+    }
+    let mut mangled = "_U".to_string();
+    for c in name.0.chars() {
+        if c.is_ascii_alphanumeric() {
+            mangled.push(c);
+        } else {
+            mangled.push_str(&format!("_{:x}_", c as u32));
+        }
+    }
+    llvm_name(&mangled)
 }
 
 fn assert_not_nil<T>(mutref: *mut T) -> *mut T {
@@ -282,6 +399,38 @@ mod tests {
         }
     }
 
+    #[test]
+    fn mangles_names() {
+        let mangle = |s: &str| into_llvm_name(Name::new(s)).into_string().unwrap();
+        assert_eq!(mangle("fib_2"), "fib_2");
+        assert_eq!(mangle("\u{394}x"), "_U_394_x");
+        assert_eq!(mangle("a_\u{3b1}"), "_Ua_5f__3b1_");
+        assert_eq!(mangle("_Ux"), "_U_5f_Ux");
+    }
+
+    #[test]
+    fn reports_failures() {
+        let missing = Path::new("/nonexistent/directory/program");
+        unsafe {
+            match compile(&parse(b"inputs; outputs;"), Emit::IR(Some(missing.into()))) {
+                Err(Error::Io { path, .. }) => assert_eq!(path, missing),
+                result => panic!("expected an I/O error, got {:?}", result),
+            }
+            let object = NamedTempFile::new().unwrap();
+            match objectify(&"define i64 @f( {".to_string(), object.path()) {
+                Err(Error::InvalidIr(message)) => assert!(!message.is_empty()),
+                result => panic!("expected an IR error, got {:?}", result),
+            }
+        }
+        match link(missing, missing) {
+            Err(Error::Linker { status, stderr }) => {
+                assert!(status.is_some());
+                assert!(!stderr.is_empty());
+            }
+            result => panic!("expected a linker error, got {:?}", result),
+        }
+    }
+
     fn parse(s: &[u8]) -> Program {
         parser::parse(s).unwrap()
     }
@@ -0,0 +1,228 @@
+use super::*;
+use analysis::{LintLevel, Lints};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Level::Error => write!(f, "error"),
+            Level::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A message for whoever wrote a program, about somewhere in its source if `span` says where.
+/// Warnings, and the errors they become when denied, have their lint's code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub code: Option<&'static str>,
+    pub message: String,
+    pub span: Option<Span>,
+}
+
+impl Diagnostic {
+    pub fn error(error: &Error) -> Diagnostic {
+        Diagnostic {
+            level: Level::Error,
+            code: None,
+            message: format!("{}", error),
+            span: error.span(),
+        }
+    }
+
+    pub fn warning(warning: &analysis::Warning) -> Diagnostic {
+        Diagnostic {
+            level: Level::Warning,
+            code: Some(warning.lint().code()),
+            message: format!("{}", warning),
+            span: warning.span(),
+        }
+    }
+
+    /// Renders the diagnostic for a terminal, quoting the line of `source`, read from `path`,
+    /// that it is about:
+    ///
+    /// ```text
+    /// error: unknown variable `y`
+    ///  --> f.math:2:5
+    ///   |
+    /// 2 | x = y + 1;
+    ///   |     ^
+    /// ```
+    pub fn render(&self, path: &str, source: &[u8]) -> String {
+        let heading = match self.code {
+            Some(code) => format!("{}[{}]: {}", self.level, code, self.message),
+            None => format!("{}: {}", self.level, self.message),
+        };
+        let span = match self.span {
+            Some(span) => span,
+            None => return format!("{}\n --> {}", heading, path),
+        };
+        let line_start = source[..span.start]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        let line_end = source[span.start..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(source.len(), |i| span.start + i);
+        let line = String::from_utf8_lossy(&source[line_start..line_end]);
+        let line = line.trim_right_matches('\r');
+        // Tabs are kept so that the underline lines up however they are shown.
+        let indent: String = line
+            .chars()
+            .take(span.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underlined = String::from_utf8_lossy(&source[span.start..span.end.min(line_end)]);
+        let underline = "^".repeat(underlined.chars().count().max(1));
+        let number = span.line.to_string();
+        let gutter = " ".repeat(number.len());
+        format!(
+            "{}\n{}--> {}:{}\n{} |\n{} | {}\n{} | {}{}",
+            heading, gutter, path, span, gutter, number, line, gutter, indent, underline
+        )
+    }
+}
+
+/// Parses and checks a program, giving every error and warning about it, along with the
+/// program if there are no errors.
+pub fn diagnose(source: &[u8]) -> (Option<Program>, Vec<Diagnostic>) {
+    diagnose_with(source, &Lints::new())
+}
+
+/// Like `diagnose`, but with warnings allowed or denied by `lints`. Denied warnings are errors.
+pub fn diagnose_with(source: &[u8], lints: &Lints) -> (Option<Program>, Vec<Diagnostic>) {
+    let (program, errors) = parser::parse_recovering(source);
+    let parsed = errors.is_empty();
+    let mut errors: Vec<_> = errors.into_iter().map(Error::ParseError).collect();
+    if parsed {
+        errors.extend(check::errors(&program).into_iter().map(Error::CheckError));
+    }
+    let mut failed = !errors.is_empty();
+    let mut diagnostics: Vec<_> = errors.iter().map(Diagnostic::error).collect();
+    // Programs with parse errors are missing statements, so would be warned about wrongly.
+    if parsed {
+        for warning in analysis::warnings(&program) {
+            let diagnostic = Diagnostic::warning(&warning);
+            match lints.level(warning.lint()) {
+                LintLevel::Allow => {}
+                LintLevel::Warn => diagnostics.push(diagnostic),
+                LintLevel::Deny => {
+                    failed = true;
+                    diagnostics.push(Diagnostic {
+                        level: Level::Error,
+                        ..diagnostic
+                    });
+                }
+            }
+        }
+    }
+    if failed {
+        (None, diagnostics)
+    } else {
+        (Some(program), diagnostics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use analysis::Lint;
+
+    #[test]
+    fn render_test() {
+        let source = b"inputs;\n\tf(n) = n * y;\nx = f(1);\noutputs x;";
+        let error = interpret(source, &vec![]).unwrap_err();
+        assert_eq!(
+            Diagnostic::error(&error).render("f.math", source),
+            format!(
+                "error: unknown variable `y`\n --> f.math:2:13\n  |\n\
+                 2 | \tf(n) = n * y;\n  | \t{}^",
+                " ".repeat(11)
+            )
+        );
+
+        let error = interpret(b"inputs n; outputs n;", &vec![]).unwrap_err();
+        assert_eq!(
+            Diagnostic::error(&error).render("f.math", b""),
+            "error: the program takes 1 input, but was given 0\n --> f.math"
+        );
+
+        let source = b"inputs;\nx = 1 + 99999999999999999999; outputs x;";
+        let error = interpret(source, &vec![]).unwrap_err();
+        assert_eq!(
+            Diagnostic::error(&error).render("f.math", source),
+            "error: integer literal `99999999999999999999` doesn't fit in 64 bits\n \
+             --> f.math:2:9\n  |\n2 | x = 1 + 99999999999999999999; outputs x;\n  |         \
+             ^^^^^^^^^^^^^^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn diagnose_test() {
+        let (program, diagnostics) = diagnose(b"inputs; x = y; z = ; f(a, a) = 1; outputs x;");
+        assert_eq!(program, None);
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages, vec!["expected a statement"]);
+
+        let (program, diagnostics) =
+            diagnose(b"inputs; x = y; f(a, a) = match a { let n => n, _ => 1 }; outputs x;");
+        assert_eq!(program, None);
+        let diagnostics: Vec<_> = diagnostics
+            .iter()
+            .map(|d| (d.level, d.message.as_str()))
+            .collect();
+        assert_eq!(
+            diagnostics,
+            vec![
+                (Level::Error, "unknown variable `y`"),
+                (Level::Error, "`f` has more than one parameter named `a`"),
+                (Level::Warning, "redundant `_` clause in `f`: every value is already matched"),
+                (Level::Warning, "function `f` is never used"),
+            ]
+        );
+
+        let (program, diagnostics) = diagnose(b"inputs; x = 1; outputs x;");
+        assert!(program.is_some());
+        assert_eq!(diagnostics, vec![]);
+    }
+
+    #[test]
+    fn lints_are_configured() {
+        let source = b"inputs a, b;\nx = a / 0; outputs x;";
+        let (program, diagnostics) = diagnose(source);
+        assert!(program.is_some());
+        let rendered: Vec<_> = diagnostics
+            .iter()
+            .map(|d| d.render("f.math", source))
+            .collect();
+        assert_eq!(
+            rendered,
+            vec![
+                "warning[W0003]: input `b` is never used\n --> f.math:1:11\n  |\n\
+                 1 | inputs a, b;\n  |           ^",
+                "warning[W0007]: division by zero in `x` saturates instead of failing\n \
+                 --> f.math:2:9\n  |\n2 | x = a / 0; outputs x;\n  |         ^",
+            ]
+        );
+
+        let mut lints = Lints::new();
+        lints.set(Lint::UnusedInput, LintLevel::Allow);
+        let (program, diagnostics) = diagnose_with(source, &lints);
+        assert!(program.is_some());
+        assert_eq!(diagnostics.len(), 1);
+
+        lints.deny_warnings = true;
+        let (program, diagnostics) = diagnose_with(source, &lints);
+        assert_eq!(program, None);
+        let diagnostics: Vec<_> = diagnostics.iter().map(|d| (d.level, d.code)).collect();
+        assert_eq!(diagnostics, vec![(Level::Error, Some("W0007"))]);
+    }
+}
@@ -0,0 +1,149 @@
+/// A layout of text that may be broken over lines. Each group is written on one line if it
+/// fits, or else with each of its own line breaks taken.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Doc {
+    Text(String),
+    /// A space, or a new line if its group is broken.
+    Line,
+    /// Nothing, or a new line if its group is broken.
+    SoftLine,
+    /// Text only written if its group is broken, such as a trailing comma.
+    IfBroken(&'static str),
+    /// Indents the lines broken within it.
+    Nest(Box<Doc>),
+    Group(Box<Doc>),
+    Concat(Vec<Doc>),
+}
+
+const INDENT: usize = 2;
+
+pub fn text<S: Into<String>>(s: S) -> Doc {
+    Doc::Text(s.into())
+}
+
+pub fn nest(doc: Doc) -> Doc {
+    Doc::Nest(box doc)
+}
+
+pub fn group(doc: Doc) -> Doc {
+    Doc::Group(box doc)
+}
+
+pub fn concat(docs: Vec<Doc>) -> Doc {
+    Doc::Concat(docs)
+}
+
+/// The docs with `,` and a line between each.
+pub fn comma_separated(docs: Vec<Doc>) -> Doc {
+    let mut separated = vec![];
+    for (i, doc) in docs.into_iter().enumerate() {
+        if i > 0 {
+            separated.push(text(","));
+            separated.push(Doc::Line);
+        }
+        separated.push(doc);
+    }
+    concat(separated)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Flat,
+    Broken,
+}
+
+/// Lays out the doc in lines of at most `width` characters where it can.
+pub fn render(doc: &Doc, width: usize) -> String {
+    let mut out = String::new();
+    let mut column = 0;
+    let mut stack = vec![(0, Mode::Broken, doc)];
+    while let Some((indent, mode, doc)) = stack.pop() {
+        match *doc {
+            Doc::Text(ref s) => {
+                out.push_str(s);
+                column += s.chars().count();
+            }
+            Doc::Line | Doc::SoftLine if mode == Mode::Broken => {
+                out.push('\n');
+                out.push_str(&" ".repeat(indent));
+                column = indent;
+            }
+            Doc::Line => {
+                out.push(' ');
+                column += 1;
+            }
+            Doc::SoftLine => {}
+            Doc::IfBroken(s) => if mode == Mode::Broken {
+                out.push_str(s);
+                column += s.chars().count();
+            },
+            Doc::Nest(ref doc) => stack.push((indent + INDENT, mode, doc)),
+            Doc::Group(ref doc) => {
+                let fits = mode == Mode::Flat
+                    || fits(width as isize - column as isize, (indent, doc), &stack);
+                stack.push((indent, if fits { Mode::Flat } else { Mode::Broken }, doc));
+            }
+            Doc::Concat(ref docs) => for doc in docs.iter().rev() {
+                stack.push((indent, mode, doc));
+            },
+        }
+    }
+    out
+}
+
+// Whether the doc fits in the space left on the line when laid out flat, along with whatever
+// follows it up to the next line break.
+fn fits(mut space: isize, (indent, doc): (usize, &Doc), rest: &[(usize, Mode, &Doc)]) -> bool {
+    let mut rest = rest.iter().rev();
+    let mut stack = vec![(indent, Mode::Flat, doc)];
+    while space >= 0 {
+        let (indent, mode, doc) = match stack.pop() {
+            Some(next) => next,
+            None => match rest.next() {
+                Some(&next) => next,
+                None => return true,
+            },
+        };
+        match *doc {
+            Doc::Text(ref s) => space -= s.chars().count() as isize,
+            Doc::Line | Doc::SoftLine if mode == Mode::Broken => return true,
+            Doc::Line => space -= 1,
+            Doc::SoftLine => {}
+            Doc::IfBroken(s) => if mode == Mode::Broken {
+                space -= s.chars().count() as isize;
+            },
+            Doc::Nest(ref doc) | Doc::Group(ref doc) => stack.push((indent, mode, doc)),
+            Doc::Concat(ref docs) => for doc in docs.iter().rev() {
+                stack.push((indent, mode, doc));
+            },
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let call = |args: Vec<&str>| {
+            group(concat(vec![
+                text("f("),
+                nest(concat(vec![
+                    Doc::SoftLine,
+                    comma_separated(args.into_iter().map(text).collect()),
+                    Doc::IfBroken(","),
+                ])),
+                Doc::SoftLine,
+                text(")"),
+            ]))
+        };
+        assert_eq!(render(&call(vec!["a", "b"]), 10), "f(a, b)");
+        assert_eq!(render(&call(vec!["a", "b"]), 6), "f(\n  a,\n  b,\n)");
+        // What follows the group on its line must fit too.
+        let nested = concat(vec![call(vec!["a"]), text(" + 1")]);
+        assert_eq!(render(&nested, 8), "f(a) + 1");
+        assert_eq!(render(&nested, 7), "f(\n  a,\n) + 1");
+    }
+}
@@ -0,0 +1,393 @@
+mod doc;
+
+use super::*;
+use self::doc::*;
+use parser::Item;
+use nom::IResult;
+
+/// Lays out a program's source canonically, breaking long lists of arguments, parameters and
+/// names, `match` clauses and the like onto indented lines so that lines are at most `width`
+/// characters where they can be. Comments are kept on their own lines, except those after a
+/// `;` on the same line, which stay there, and block comments within a statement, which follow
+/// its `;`. Line comments within a statement, and any comments after them, go on lines of their
+/// own after it.
+pub fn format_source(source: &[u8], width: usize) -> Result<String, parser::Error> {
+    let (inputs, items, outputs) = parser::parse_items(source)?;
+    let mut docs = vec![names_doc("inputs", &inputs)];
+    docs.extend(items.iter().map(|&(_, ref item)| item_doc(item)));
+    docs.push(names_doc("outputs", &outputs));
+
+    let comments = comments(source);
+    let mut out = String::new();
+    // Where the previous statement's `;`, or the source, ended.
+    let mut previous_end = 0;
+    for i in 0..docs.len() + 1 {
+        let (start, end) = if i < docs.len() {
+            let start = skip_blank(source, previous_end);
+            (start, terminator(source, start))
+        } else {
+            (source.len(), source.len())
+        };
+        let mut after = previous_end;
+        let mut trailing = i > 0;
+        for &(comment_start, comment_end) in &comments {
+            if comment_start < previous_end || comment_start >= start {
+                continue;
+            }
+            let gap = &source[after..comment_start];
+            trailing = trailing && !gap.contains(&b'\n');
+            if trailing {
+                out.push(' ');
+            } else {
+                new_line(&mut out, gap);
+            }
+            out.push_str(&comment_text(source, comment_start, comment_end));
+            after = comment_end;
+        }
+        if i == docs.len() {
+            break;
+        }
+        new_line(&mut out, &source[after..start]);
+        out.push_str(&render(&docs[i], width));
+        // Whether a line comment has been written, which would take in anything after it.
+        let mut line_ended = false;
+        for &(comment_start, comment_end) in &comments {
+            if start < comment_start && comment_start < end {
+                line_ended = line_ended || !source[comment_start..].starts_with(b"/*");
+                out.push(if line_ended { '\n' } else { ' ' });
+                out.push_str(&comment_text(source, comment_start, comment_end));
+            }
+        }
+        previous_end = end + 1;
+    }
+    out.push('\n');
+    Ok(out)
+}
+
+// Starts a new line, leaving an empty line if the blanks between this and what came before had
+// one, unless nothing has been written yet.
+fn new_line(out: &mut String, blanks: &[u8]) {
+    if out.is_empty() {
+        return;
+    }
+    out.push('\n');
+    if blanks.iter().filter(|&&b| b == b'\n').count() > 1 {
+        out.push('\n');
+    }
+}
+
+// Where each comment in the source starts and ends, not including the line ending after a line
+// comment. Comments are found as `parser::blank` finds them, which the language allows anywhere.
+fn comments(source: &[u8]) -> Vec<(usize, usize)> {
+    let mut comments = vec![];
+    let mut i = 0;
+    while i < source.len() {
+        let rest = &source[i..];
+        let length = if rest.starts_with(b"#") || rest.starts_with(b"//") {
+            rest.iter().position(|&b| b == b'\n').unwrap_or(rest.len())
+        } else if rest.starts_with(b"/*") {
+            match rest.windows(2).skip(2).position(|w| w == b"*/") {
+                Some(end) => end + 4,
+                None => rest.len(),
+            }
+        } else {
+            i += 1;
+            continue;
+        };
+        comments.push((i, i + length));
+        i += length;
+    }
+    comments
+}
+
+fn comment_text(source: &[u8], start: usize, end: usize) -> String {
+    String::from_utf8_lossy(&source[start..end])
+        .trim_right()
+        .to_string()
+}
+
+fn skip_blank(source: &[u8], from: usize) -> usize {
+    let rest = match parser::blank(&source[from..]) {
+        IResult::Done(rest, _) => rest,
+        _ => &source[from..],
+    };
+    source.len() - rest.len()
+}
+
+// Where the `;` ending the statement starting at `start` is.
+fn terminator(source: &[u8], start: usize) -> usize {
+    let mut i = start;
+    loop {
+        i = skip_blank(source, i);
+        if i >= source.len() || source[i] == b';' {
+            return i;
+        }
+        i += 1;
+    }
+}
+
+fn names_doc(keyword: &str, names: &[Name]) -> Doc {
+    if names.is_empty() {
+        return text(format!("{};", keyword));
+    }
+    let names = names.iter().map(|name| text(name.0.clone())).collect();
+    group(concat(vec![
+        text(keyword),
+        nest(concat(vec![Doc::Line, comma_separated(names)])),
+        text(";"),
+    ]))
+}
+
+fn item_doc(item: &Item) -> Doc {
+    match *item {
+        Item::Statement(ref statement) => statement_doc(statement),
+        Item::FnClause(ref clause) => {
+            let params = clause.params.iter().map(param_doc).collect();
+            definition_doc(&clause.name, params, &clause.body, &clause.bindings)
+        }
+    }
+}
+
+fn statement_doc(statement: &Statement) -> Doc {
+    match *statement {
+        Statement::VarAssignment(ref name, ref expression, _) => concat(vec![
+            text(format!("{} = ", name)),
+            expression_doc(expression),
+            text(";"),
+        ]),
+        Statement::FnDefinition(ref name, ref params, ref body, ref bindings, _) => {
+            let params = params.iter().map(|param| text(param.0.clone())).collect();
+            definition_doc(name, params, body, bindings)
+        }
+    }
+}
+
+fn param_doc(param: &Matcher) -> Doc {
+    match *param {
+        Matcher::Binding(ref name, ref matcher) if **matcher == Matcher::Wildcard => {
+            text(name.0.clone())
+        }
+        ref matcher => text(format!("{}", matcher)),
+    }
+}
+
+fn definition_doc(
+    name: &Name,
+    params: Vec<Doc>,
+    body: &Expression,
+    bindings: &[(Name, Expression)],
+) -> Doc {
+    let mut docs = vec![call_doc(name, params), text(" = "), expression_doc(body)];
+    if !bindings.is_empty() {
+        docs.push(group(nest(concat(vec![
+            Doc::Line,
+            text("where "),
+            group(nest(bindings_doc(bindings))),
+        ]))));
+    }
+    docs.push(text(";"));
+    concat(docs)
+}
+
+fn call_doc(name: &Name, args: Vec<Doc>) -> Doc {
+    if args.is_empty() {
+        return text(format!("{}()", name));
+    }
+    group(concat(vec![
+        text(format!("{}(", name)),
+        nest(concat(vec![Doc::SoftLine, comma_separated(args)])),
+        Doc::SoftLine,
+        text(")"),
+    ]))
+}
+
+fn bindings_doc(bindings: &[(Name, Expression)]) -> Doc {
+    comma_separated(
+        bindings
+            .iter()
+            .map(|&(ref name, ref value)| {
+                concat(vec![text(format!("{} = ", name)), expression_doc(value)])
+            })
+            .collect(),
+    )
+}
+
+// Groups are written as `Display` writes them, so that the layout reads back the same. Lines
+// are broken before operators.
+fn expression_doc(expression: &Expression) -> Doc {
+    match *expression {
+        Expression::Operand(ref operand) => operand_doc(operand),
+        Expression::Spanned(_, ref expression) => expression_doc(expression),
+        Expression::Operation(operator, ref lhs, ref rhs) => {
+            let lhs_grouped = lhs.groups_as_lhs_of(operator);
+            let rhs_grouped = match *rhs.unspanned() {
+                Expression::Operation(inner, _, _) => operator.groups_rhs(inner),
+                _ => false,
+            };
+            group(concat(vec![
+                grouped_doc(lhs, lhs_grouped),
+                nest(concat(vec![
+                    Doc::Line,
+                    text(format!("{} ", operator)),
+                    grouped_doc(rhs, rhs_grouped),
+                ])),
+            ]))
+        }
+    }
+}
+
+fn grouped_doc(expression: &Expression, grouped: bool) -> Doc {
+    if grouped {
+        concat(vec![text("("), expression_doc(expression), text(")")])
+    } else {
+        expression_doc(expression)
+    }
+}
+
+fn operand_doc(operand: &Operand) -> Doc {
+    match *operand {
+        Operand::I64(n) => text(n.to_string()),
+        Operand::Group(ref expression) => grouped_doc(expression, true),
+        Operand::VarSubstitution(ref name) => text(name.0.clone()),
+        Operand::FnApplication(ref name, ref args) => {
+            call_doc(name, args.iter().map(expression_doc).collect())
+        }
+        Operand::Match(ref match_) => match_doc(match_),
+        Operand::If(ref condition, ref then, ref else_) => group(concat(vec![
+            text("if "),
+            expression_doc(condition),
+            nest(concat(vec![
+                Doc::Line,
+                text("then "),
+                expression_doc(then),
+                Doc::Line,
+                text("else "),
+                expression_doc(else_),
+            ])),
+        ])),
+        Operand::Not(ref operand) => concat(vec![text("!"), operand_doc(operand)]),
+        Operand::Negate(ref operand) => concat(vec![text("-"), operand_doc(operand)]),
+        Operand::Let(ref bindings, ref body) => group(concat(vec![
+            text("let "),
+            nest(bindings_doc(bindings)),
+            Doc::Line,
+            text("in "),
+            expression_doc(body),
+        ])),
+    }
+}
+
+fn match_doc(match_: &Match) -> Doc {
+    let with = if match_.with.len() == 1 {
+        expression_doc(&match_.with[0])
+    } else {
+        group(concat(vec![
+            text("("),
+            nest(comma_separated(match_.with.iter().map(expression_doc).collect())),
+            text(")"),
+        ]))
+    };
+    let mut clauses = vec![];
+    for &(ref matcher, ref guard, ref expression) in &match_.clauses {
+        let mut clause = vec![text(format!("{}", matcher))];
+        if let Some(ref guard) = *guard {
+            clause.push(text(" if "));
+            clause.push(expression_doc(guard));
+        }
+        clause.push(text(" => "));
+        clause.push(expression_doc(expression));
+        clauses.push(concat(clause));
+    }
+    clauses.push(concat(vec![text("_ => "), expression_doc(&match_.default)]));
+    group(concat(vec![
+        text("match "),
+        with,
+        text(" {"),
+        nest(concat(vec![
+            Doc::Line,
+            comma_separated(clauses),
+            Doc::IfBroken(","),
+        ])),
+        Doc::Line,
+        text("}"),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quickcheck::{QuickCheck, StdGen};
+    use rand::thread_rng;
+
+    #[test]
+    fn breaks_long_lines() {
+        let source = b"inputs n;\n\
+                       fib(n) = match n { 0 => 0, 1 => 1, _ => fib(n - 1) + fib(n - 2) };\n\
+                       add(a, b, c) = a + b + c;\n\
+                       m = add(fib(n), fib(n + 1), fib(n + 2)); outputs m;";
+        assert_eq!(
+            format_source(source, 39).unwrap(),
+            "inputs n;\n\
+             fib(n) = match n {\n  0 => 0,\n  1 => 1,\n  _ => fib(n - 1) + fib(n - 2),\n};\n\
+             add(a, b, c) = a + b + c;\n\
+             m = add(\n  fib(n),\n  fib(n + 1),\n  fib(n + 2)\n);\n\
+             outputs m;\n"
+        );
+        assert_eq!(
+            format_source(source, 100).unwrap(),
+            "inputs n;\n\
+             fib(n) = match n { 0 => 0, 1 => 1, _ => fib(n - 1) + fib(n - 2) };\n\
+             add(a, b, c) = a + b + c;\n\
+             m = add(fib(n), fib(n + 1), fib(n + 2));\n\
+             outputs m;\n"
+        );
+    }
+
+    #[test]
+    fn keeps_piecewise_definitions() {
+        assert_eq!(
+            format_source(b"inputs;f(0,m)=m;f(n @ 1..=9,_)=k where k=n;f(n,m)=0;outputs;", 80)
+                .unwrap(),
+            "inputs;\nf(0, m) = m;\nf(n @ 1..=9, _) = k where k = n;\nf(n, m) = 0;\noutputs;\n"
+        );
+        assert!(format_source(b"inputs; x = ; outputs;", 80).is_err());
+    }
+
+    #[test]
+    fn keeps_comments() {
+        let source = b"# The program.\n\ninputs a; # Trailing.\n\
+                       /// Doc.\nx = a + /* inner */ 1;\n\n\n\
+                       // Before outputs.\noutputs x; /* end */\n# Last.\n";
+        assert_eq!(
+            format_source(source, 80).unwrap(),
+            "# The program.\n\ninputs a; # Trailing.\n/// Doc.\nx = a + 1; /* inner */\n\n\
+             // Before outputs.\noutputs x; /* end */\n# Last.\n"
+        );
+
+        // Line comments within a statement each keep a line of their own.
+        let source = b"inputs a; x = a + # one\n 2 /* two */ # three\n * 3; outputs x;";
+        let formatted = "inputs a;\nx = a + 2 * 3;\n# one\n/* two */\n# three\noutputs x;\n";
+        assert_eq!(format_source(source, 80).unwrap(), formatted);
+        assert_eq!(format_source(formatted.as_bytes(), 80).unwrap(), formatted);
+        let source = b"inputs a; x = a /* one */ + /* two */ 1; outputs x;";
+        assert_eq!(
+            format_source(source, 80).unwrap(),
+            "inputs a;\nx = a + 1; /* one */ /* two */\noutputs x;\n"
+        );
+    }
+
+    fn formatting_is_idempotent_prop(program: Program, width: u8) -> bool {
+        let width = 20 + width as usize % 80;
+        let formatted = format_source(format!("{}", program).as_bytes(), width).unwrap();
+        parser::parse(formatted.as_bytes()).unwrap() == program
+            && format_source(formatted.as_bytes(), width).unwrap() == formatted
+    }
+
+    #[test]
+    fn formatting_is_idempotent() {
+        for size in 1..11 {
+            let mut qc = QuickCheck::new().gen(StdGen::new(thread_rng(), size));
+            qc.quickcheck(formatting_is_idempotent_prop as fn(Program, u8) -> bool);
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use super::*;
+use std::cmp;
 use std::collections::HashMap;
 
-// FIXME: Function parameter names should all differ
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     UnknownVariable(Name),
@@ -17,13 +17,59 @@ pub enum Error {
     },
 }
 
+impl Error {
+    /// Where in the source the error is, if it is about anywhere in particular.
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            Error::UnknownVariable(ref name)
+            | Error::UnknownFunction(ref name)
+            | Error::IncorrectArgumentCount { ref name, .. } => located(name.1),
+            Error::IncorrectInputCount { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::UnknownVariable(ref name) => write!(f, "unknown variable `{}`", name),
+            Error::UnknownFunction(ref name) => write!(f, "unknown function `{}`", name),
+            Error::IncorrectArgumentCount {
+                ref name,
+                params_count,
+                provided_count,
+            } => write!(
+                f,
+                "`{}` takes {}, but was given {}",
+                name,
+                count(params_count, "argument"),
+                provided_count
+            ),
+            Error::IncorrectInputCount {
+                inputs_count,
+                provided_count,
+            } => write!(
+                f,
+                "the program takes {}, but was given {}",
+                count(inputs_count, "input"),
+                provided_count
+            ),
+        }
+    }
+}
+
 pub fn execute(program: &Program, inputs: &Vec<i64>) -> Result<Vec<i64>, Error> {
     let mut interpreter = Interpreter::new();
     interpreter.run(&program, inputs)
 }
 
+/// Evaluates an expression that uses no variables or functions.
+pub fn evaluate(expression: &Expression) -> Result<i64, Error> {
+    Interpreter::new().expression(expression, &HashMap::new(), &HashMap::new())
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Function(Vec<Name>, Expression);
+pub struct Function(Vec<Name>, Expression, Vec<(Name, Expression)>);
 
 #[derive(Debug, Clone)]
 pub struct Interpreter {
@@ -66,14 +112,14 @@ impl Interpreter {
 
     pub fn statement(&mut self, statement: &Statement) -> Result<(), Error> {
         match statement {
-            &Statement::VarAssignment(ref name, ref expr) => {
+            &Statement::VarAssignment(ref name, ref expr, _) => {
                 let variables = self.variables.clone();
                 let functions = self.functions.clone();
                 let expr_value = self.expression(expr, &variables, &functions)?;
                 self.variables.insert(name.clone(), expr_value);
             }
-            &Statement::FnDefinition(ref name, ref params, ref expr) => {
-                let function = Function(params.clone(), expr.clone());
+            &Statement::FnDefinition(ref name, ref params, ref expr, ref bindings, _) => {
+                let function = Function(params.clone(), expr.clone(), bindings.clone());
                 self.functions.insert(name.clone(), function);
                 self.function_frames
                     .insert(name.clone(), self.functions.clone());
@@ -93,6 +139,7 @@ impl Interpreter {
             &Expression::Operation(ref operator, ref expr1, ref expr2) => {
                 self.operation(operator, expr1, expr2, variables, functions)
             }
+            &Expression::Spanned(_, ref expr) => self.expression(expr, variables, functions),
         }
     }
 
@@ -105,8 +152,21 @@ impl Interpreter {
         functions: &HashMap<Name, Function>,
     ) -> Result<i64, Error> {
         let value1 = self.expression(operand1, variables, functions)?;
+        // Logical operators only evaluate their right operand if it affects the result.
+        match operator {
+            &Operator::Or if value1 != 0 => return Ok(1),
+            &Operator::And if value1 == 0 => return Ok(0),
+            _ => {}
+        }
         let value2 = self.expression(operand2, variables, functions)?;
         Ok(match operator {
+            &Operator::Or | &Operator::And => (value2 != 0) as i64,
+            &Operator::Equal => (value1 == value2) as i64,
+            &Operator::NotEqual => (value1 != value2) as i64,
+            &Operator::LessThan => (value1 < value2) as i64,
+            &Operator::LessThanOrEqual => (value1 <= value2) as i64,
+            &Operator::GreaterThan => (value1 > value2) as i64,
+            &Operator::GreaterThanOrEqual => (value1 >= value2) as i64,
             &Operator::Add => value1.saturating_add(value2),
             &Operator::Subtract => value1.saturating_sub(value2),
             &Operator::Multiply => value1.saturating_mul(value2),
@@ -121,6 +181,40 @@ impl Interpreter {
             } else {
                 value1.wrapping_div(value2)
             },
+            // Remainders by zero leave the numerator unchanged.
+            &Operator::Remainder => if value2 == 0 {
+                value1
+            } else {
+                value1.wrapping_rem(value2)
+            },
+            // Euclidean remainders are never negative, except that those by zero also leave
+            // the numerator unchanged.
+            &Operator::Modulo => {
+                let remainder = if value2 == 0 {
+                    value1
+                } else {
+                    value1.wrapping_rem(value2)
+                };
+                if remainder >= 0 {
+                    remainder
+                } else if value2 < 0 {
+                    remainder.wrapping_sub(value2)
+                } else {
+                    remainder.wrapping_add(value2)
+                }
+            }
+            &Operator::Power => saturating_pow(value1, value2),
+            &Operator::BitOr => value1 | value2,
+            &Operator::BitXor => value1 ^ value2,
+            &Operator::BitAnd => value1 & value2,
+            // Shift counts outside `0..64` shift every bit out, leaving zeroes for `<<` and
+            // copies of the sign bit for `>>`.
+            &Operator::ShiftLeft => if (value2 as u64) < 64 {
+                value1 << value2
+            } else {
+                0
+            },
+            &Operator::ShiftRight => value1 >> cmp::min(value2 as u64, 63),
         })
     }
 
@@ -138,6 +232,28 @@ impl Interpreter {
                 self.function_call(name, args, variables, functions)
             }
             &Operand::Match(ref match_) => self.match_(match_, variables, functions),
+            &Operand::Not(ref operand) => {
+                Ok((self.operand(operand, variables, functions)? == 0) as i64)
+            }
+            &Operand::Negate(ref operand) => {
+                Ok(0i64.saturating_sub(self.operand(operand, variables, functions)?))
+            }
+            &Operand::If(ref condition, ref then, ref else_) => {
+                // Only the branch that is taken gets evaluated.
+                if self.expression(condition, variables, functions)? != 0 {
+                    self.expression(then, variables, functions)
+                } else {
+                    self.expression(else_, variables, functions)
+                }
+            }
+            &Operand::Let(ref bindings, ref body) => {
+                let mut scope = variables.clone();
+                for &(ref name, ref value) in bindings {
+                    let value = self.expression(value, &scope, functions)?;
+                    scope.insert(name.clone(), value);
+                }
+                self.expression(body, &scope, functions)
+            }
         }
     }
 
@@ -155,7 +271,7 @@ impl Interpreter {
         caller_variables: &HashMap<Name, i64>,
         caller_functions: &HashMap<Name, Function>,
     ) -> Result<i64, Error> {
-        let Function(ref params, ref expr) = caller_functions
+        let Function(ref params, ref expr, ref bindings) = caller_functions
             .get(&name)
             .ok_or_else(|| Error::UnknownFunction(name.clone()))?
             .clone();
@@ -173,7 +289,11 @@ impl Interpreter {
         for arg_expr in arg_exprs {
             args.push(self.expression(arg_expr, caller_variables, caller_functions)?);
         }
-        let variables = params.iter().cloned().zip(args.into_iter()).collect();
+        let mut variables: HashMap<_, _> = params.iter().cloned().zip(args.into_iter()).collect();
+        for &(ref name, ref value) in bindings {
+            let value = self.expression(value, &variables, &functions)?;
+            variables.insert(name.clone(), value);
+        }
         let result = self.expression(expr, &variables, &functions);
         return result;
     }
@@ -184,22 +304,119 @@ impl Interpreter {
         variables: &HashMap<Name, i64>,
         functions: &HashMap<Name, Function>,
     ) -> Result<i64, Error> {
-        // with: Box<Expression>
-        // clauses: Vec<(Matcher, Expression)>
+        // with: Vec<Expression>
+        // clauses: Vec<(Matcher, Option<Expression>, Expression)>
         // default: Option<Box<Expression>>
-        let with = self.expression(&match_.with, variables, functions)?;
-        for &(ref matcher, ref expression) in &match_.clauses {
-            match matcher {
-                &Matcher::Value(ref value) => {
-                    let value = self.expression(value, variables, functions)?;
-                    if with == value {
-                        return self.expression(expression, variables, functions);
-                    }
+        let mut with = vec![];
+        for expression in &match_.with {
+            with.push(self.expression(expression, variables, functions)?);
+        }
+        for &(ref matcher, ref guard, ref expression) in &match_.clauses {
+            if !self.matcher(matcher, &with, variables, functions)? {
+                continue;
+            }
+            let bindings = matcher.bindings();
+            let mut scope;
+            let variables = if bindings.is_empty() {
+                variables
+            } else {
+                scope = variables.clone();
+                for (i, name) in bindings {
+                    scope.insert(name, with[i]);
+                }
+                &scope
+            };
+            // Guards are only evaluated once their pattern has matched.
+            if let &Some(ref guard) = guard {
+                if self.expression(guard, variables, functions)? == 0 {
+                    continue;
                 }
             }
+            return self.expression(expression, variables, functions);
         }
         self.expression(&match_.default, variables, functions)
     }
+
+    // Every matcher but a `Tuple` only looks at the first value.
+    fn matcher(
+        &mut self,
+        matcher: &Matcher,
+        values: &[i64],
+        variables: &HashMap<Name, i64>,
+        functions: &HashMap<Name, Function>,
+    ) -> Result<bool, Error> {
+        let with = values[0];
+        match matcher {
+            &Matcher::Value(ref value) => {
+                let value = self.expression(value, variables, functions)?;
+                Ok(with == value)
+            }
+            &Matcher::Range {
+                ref start,
+                ref end,
+                inclusive,
+            } => {
+                let start = self.expression(start, variables, functions)?;
+                let end = self.expression(end, variables, functions)?;
+                Ok(start <= with && if inclusive { with <= end } else { with < end })
+            }
+            &Matcher::Alternatives(ref matchers) => {
+                // Later alternatives are only evaluated if earlier ones fail to match.
+                for matcher in matchers {
+                    if self.matcher(matcher, values, variables, functions)? {
+                        return Ok(true);
+                    }
+                }
+                Ok(false)
+            }
+            &Matcher::Wildcard => Ok(true),
+            &Matcher::Binding(_, ref matcher) => {
+                self.matcher(matcher, values, variables, functions)
+            }
+            &Matcher::Tuple(ref matchers) => {
+                for (i, matcher) in matchers.iter().enumerate() {
+                    if !self.matcher(matcher, &values[i..], variables, functions)? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+}
+
+// Negative exponents truncate `1 / value1.pow(-value2)` towards zero, saturating like division.
+fn saturating_pow(base: i64, exponent: i64) -> i64 {
+    if exponent < 0 {
+        return match base {
+            0 => i64::max_value(),
+            1 => 1,
+            -1 => if exponent % 2 == 0 { 1 } else { -1 },
+            _ => 0,
+        };
+    }
+    let saturated = if base < 0 && exponent % 2 != 0 {
+        i64::min_value()
+    } else {
+        i64::max_value()
+    };
+    let (mut result, mut base, mut exponent) = (1i64, base, exponent);
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = match result.checked_mul(base) {
+                Some(result) => result,
+                None => return saturated,
+            };
+        }
+        exponent >>= 1;
+        if exponent > 0 {
+            base = match base.checked_mul(base) {
+                Some(base) => base,
+                None => return saturated,
+            };
+        }
+    }
+    result
 }
 
 #[cfg(test)]
@@ -291,11 +508,12 @@ mod tests {
             Function(
                 vec![as_name("a")],
                 Expression::Operand(Operand::VarSubstitution(as_name("a"))),
+                vec![],
             ),
         );
         assert_eq!(
             i.functions[&as_name("f")],
-            Function(vec![as_name("a")], expression(b"3 * a;").unwrap().1,)
+            Function(vec![as_name("a")], expression(b"3 * a;").unwrap().1, vec![])
         );
     }
 
@@ -311,6 +529,7 @@ mod tests {
             Function(
                 vec![as_name("a")],
                 Expression::Operand(Operand::VarSubstitution(as_name("a"))),
+                vec![],
             ),
         );
         assert_eq!(
@@ -318,6 +537,7 @@ mod tests {
             Function(
                 vec![as_name("a"), as_name("b")],
                 expression(b"a + b;").unwrap().1,
+                vec![],
             )
         );
     }
@@ -498,7 +718,243 @@ mod tests {
         assert_eq!(i.variables[&as_name("z")], 3);
     }
 
+    #[test]
+    fn match_ranges_and_alternatives() {
+        let mut i = Interpreter::new();
+        i.statement(
+            &statement(b"f(x) = match x { 0 => 0, 1..3 => 1, 3..=4 => 2, 5 | 7..9 => 3, _ => 4 };")
+                .unwrap()
+                .1,
+        ).unwrap();
+        let expected = vec![4, 0, 1, 1, 2, 2, 3, 4, 3, 3, 4];
+        for (n, expected) in (-1..10).zip(expected) {
+            i.statement(&statement(format!("y = f({});", n).as_bytes()).unwrap().1)
+                .unwrap();
+            assert_eq!(i.variables[&as_name("y")], expected);
+        }
+    }
+
+    #[test]
+    fn match_guards_fall_through() {
+        let mut i = Interpreter::new();
+        let f = b"f(n) = match n { 0..10 if n mod 2 == 0 => 1, 0..10 => 2, _ if n > 100 => 3, \
+                  _ => 4 };";
+        i.statement(&statement(f).unwrap().1).unwrap();
+        i.statement(&statement(b"a = f(4);").unwrap().1).unwrap();
+        i.statement(&statement(b"b = f(5);").unwrap().1).unwrap();
+        i.statement(&statement(b"c = f(500);").unwrap().1).unwrap();
+        i.statement(&statement(b"d = f(50);").unwrap().1).unwrap();
+        i.statement(&statement(b"e = match 1 { 2 if unknown => 1, _ => 0 };").unwrap().1)
+            .unwrap();
+        assert_eq!(i.variables[&as_name("a")], 1);
+        assert_eq!(i.variables[&as_name("b")], 2);
+        assert_eq!(i.variables[&as_name("c")], 3);
+        assert_eq!(i.variables[&as_name("d")], 4);
+        assert_eq!(i.variables[&as_name("e")], 0);
+    }
+
+    #[test]
+    fn match_bindings_are_scoped_to_their_clause() {
+        let mut i = Interpreter::new();
+        i.statement(&statement(b"n = 7;").unwrap().1).unwrap();
+        let f = b"f(x) = match x * 2 { n @ 0..10 => n + 100, let n if n > 50 => -n, _ => n };";
+        i.statement(&statement(f).unwrap().1).unwrap();
+        i.statement(&statement(b"a = f(3);").unwrap().1).unwrap();
+        i.statement(&statement(b"b = f(30);").unwrap().1).unwrap();
+        i.statement(&statement(b"c = match 2 { let n if n > 5 => 0, _ => n };").unwrap().1)
+            .unwrap();
+        assert_eq!(i.variables[&as_name("a")], 106);
+        assert_eq!(i.variables[&as_name("b")], -60);
+        assert_eq!(i.variables[&as_name("c")], 7);
+        assert_eq!(
+            i.statement(&statement(b"d = f(10);").unwrap().1),
+            Err(Error::UnknownVariable(as_name("n")))
+        );
+    }
+
+    #[test]
+    fn tuple_matches_check_every_value() {
+        let mut i = Interpreter::new();
+        let f = b"f(m, n) = match (m, n + 1) { (0, let k) => k, (let j, 1 | 2) => j * 10, \
+                  (_, let k) if k > 5 => -k, _ => 0 };";
+        i.statement(&statement(f).unwrap().1).unwrap();
+        i.statement(&statement(b"a = f(0, 7);").unwrap().1).unwrap();
+        i.statement(&statement(b"b = f(3, 1);").unwrap().1).unwrap();
+        i.statement(&statement(b"c = f(3, 9);").unwrap().1).unwrap();
+        i.statement(&statement(b"d = f(3, 3);").unwrap().1).unwrap();
+        assert_eq!(i.variables[&as_name("a")], 8);
+        assert_eq!(i.variables[&as_name("b")], 30);
+        assert_eq!(i.variables[&as_name("c")], -10);
+        assert_eq!(i.variables[&as_name("d")], 0);
+    }
+
+    #[test]
+    fn comparisons_yield_zero_or_one() {
+        let mut i = Interpreter::new();
+        i.statement(&statement(b"a = 1 < 2;").unwrap().1).unwrap();
+        i.statement(&statement(b"b = 2 <= 1;").unwrap().1).unwrap();
+        i.statement(&statement(b"c = 3 == 1 + 2;").unwrap().1).unwrap();
+        i.statement(&statement(b"d = 3 != 3;").unwrap().1).unwrap();
+        i.statement(&statement(b"e = -1 > -2;").unwrap().1).unwrap();
+        i.statement(&statement(b"f = 5 >= 5;").unwrap().1).unwrap();
+        assert_eq!(i.variables[&as_name("a")], 1);
+        assert_eq!(i.variables[&as_name("b")], 0);
+        assert_eq!(i.variables[&as_name("c")], 1);
+        assert_eq!(i.variables[&as_name("d")], 0);
+        assert_eq!(i.variables[&as_name("e")], 1);
+        assert_eq!(i.variables[&as_name("f")], 1);
+    }
+
+    #[test]
+    fn remainders_saturate() {
+        let mut i = Interpreter::new();
+        i.statement(&statement(b"a = -7 % 2;").unwrap().1).unwrap();
+        i.statement(&statement(b"b = -7 mod 2;").unwrap().1).unwrap();
+        i.statement(&statement(b"c = 7 mod -2;").unwrap().1).unwrap();
+        i.statement(&statement(b"d = -7 mod -2;").unwrap().1).unwrap();
+        i.statement(&statement(b"e = 5 % 0;").unwrap().1).unwrap();
+        i.statement(&statement(b"f = -5 mod 0;").unwrap().1).unwrap();
+        i.statement(&statement(b"g = -9223372036854775808 % -1;").unwrap().1)
+            .unwrap();
+        i.statement(&statement(b"h = -9223372036854775807 mod -9223372036854775808;").unwrap().1)
+            .unwrap();
+        assert_eq!(i.variables[&as_name("a")], -1);
+        assert_eq!(i.variables[&as_name("b")], 1);
+        assert_eq!(i.variables[&as_name("c")], 1);
+        assert_eq!(i.variables[&as_name("d")], 1);
+        assert_eq!(i.variables[&as_name("e")], 5);
+        assert_eq!(i.variables[&as_name("f")], -5);
+        assert_eq!(i.variables[&as_name("g")], 0);
+        assert_eq!(i.variables[&as_name("h")], 1);
+    }
+
+    #[test]
+    fn powers_saturate() {
+        let mut i = Interpreter::new();
+        i.statement(&statement(b"a = 2 ^ 3 ^ 2;").unwrap().1).unwrap();
+        i.statement(&statement(b"b = (-3) ^ 3;").unwrap().1).unwrap();
+        i.statement(&statement(b"c = 2 ^ 64;").unwrap().1).unwrap();
+        i.statement(&statement(b"d = (-2) ^ 63;").unwrap().1).unwrap();
+        i.statement(&statement(b"e = (-2) ^ 65;").unwrap().1).unwrap();
+        i.statement(&statement(b"f = 2 ^ -1;").unwrap().1).unwrap();
+        i.statement(&statement(b"g = (-1) ^ -3;").unwrap().1).unwrap();
+        i.statement(&statement(b"h = 0 ^ -2;").unwrap().1).unwrap();
+        i.statement(&statement(b"j = 7 ^ 0;").unwrap().1).unwrap();
+        // `-` applies to the whole power.
+        i.statement(&statement(b"k = -3 ^ 2;").unwrap().1).unwrap();
+        i.statement(&statement(b"l = -j ^ 2 * 2;").unwrap().1).unwrap();
+        i.statement(&statement(b"m = (-j) ^ 2;").unwrap().1).unwrap();
+        assert_eq!(i.variables[&as_name("a")], 512);
+        assert_eq!(i.variables[&as_name("b")], -27);
+        assert_eq!(i.variables[&as_name("c")], i64::max_value());
+        assert_eq!(i.variables[&as_name("d")], i64::min_value());
+        assert_eq!(i.variables[&as_name("e")], i64::min_value());
+        assert_eq!(i.variables[&as_name("f")], 0);
+        assert_eq!(i.variables[&as_name("g")], -1);
+        assert_eq!(i.variables[&as_name("h")], i64::max_value());
+        assert_eq!(i.variables[&as_name("j")], 1);
+        assert_eq!(i.variables[&as_name("k")], -9);
+        assert_eq!(i.variables[&as_name("l")], -2);
+        assert_eq!(i.variables[&as_name("m")], 1);
+    }
+
+    #[test]
+    fn bitwise_operators() {
+        let mut i = Interpreter::new();
+        i.statement(&statement(b"a = 12 | 3 xor 5 & 6;").unwrap().1).unwrap();
+        i.statement(&statement(b"b = 1 << 63;").unwrap().1).unwrap();
+        i.statement(&statement(b"c = 1 << 64;").unwrap().1).unwrap();
+        i.statement(&statement(b"d = 1 << -1;").unwrap().1).unwrap();
+        i.statement(&statement(b"e = -8 >> 2;").unwrap().1).unwrap();
+        i.statement(&statement(b"f = -8 >> 64;").unwrap().1).unwrap();
+        i.statement(&statement(b"g = 8 >> -1;").unwrap().1).unwrap();
+        assert_eq!(i.variables[&as_name("a")], 15);
+        assert_eq!(i.variables[&as_name("b")], i64::min_value());
+        assert_eq!(i.variables[&as_name("c")], 0);
+        assert_eq!(i.variables[&as_name("d")], 0);
+        assert_eq!(i.variables[&as_name("e")], -2);
+        assert_eq!(i.variables[&as_name("f")], -1);
+        assert_eq!(i.variables[&as_name("g")], 0);
+    }
+
+    #[test]
+    fn negation_saturates() {
+        let mut i = Interpreter::new();
+        i.statement(&statement(b"a = -(1 + 2);").unwrap().1).unwrap();
+        i.statement(&statement(b"b = -a - -a;").unwrap().1).unwrap();
+        i.statement(&statement(b"c = -(-9223372036854775807 - 1);").unwrap().1).unwrap();
+        i.statement(&statement(b"d = +a;").unwrap().1).unwrap();
+        assert_eq!(i.variables[&as_name("a")], -3);
+        assert_eq!(i.variables[&as_name("b")], 0);
+        assert_eq!(i.variables[&as_name("c")], i64::max_value());
+        assert_eq!(i.variables[&as_name("d")], -3);
+    }
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let mut i = Interpreter::new();
+        i.statement(&statement(b"a = 2 && -3;").unwrap().1).unwrap();
+        i.statement(&statement(b"b = 0 || 0;").unwrap().1).unwrap();
+        i.statement(&statement(b"c = !5 || !0;").unwrap().1).unwrap();
+        i.statement(&statement(b"d = 0 && unknown;").unwrap().1).unwrap();
+        i.statement(&statement(b"e = 7 || unknown(1);").unwrap().1)
+            .unwrap();
+        assert_eq!(i.variables[&as_name("a")], 1);
+        assert_eq!(i.variables[&as_name("b")], 0);
+        assert_eq!(i.variables[&as_name("c")], 1);
+        assert_eq!(i.variables[&as_name("d")], 0);
+        assert_eq!(i.variables[&as_name("e")], 1);
+        assert_eq!(
+            i.statement(&statement(b"f = 1 && unknown;").unwrap().1),
+            Err(Error::UnknownVariable(as_name("unknown")))
+        );
+    }
+
+    #[test]
+    fn if_evaluates_only_the_taken_branch() {
+        let mut i = Interpreter::new();
+        i.statement(&statement(b"f(a) = if a then 10 / a else 0;").unwrap().1)
+            .unwrap();
+        i.statement(&statement(b"x = f(2);").unwrap().1).unwrap();
+        i.statement(&statement(b"y = f(0);").unwrap().1).unwrap();
+        i.statement(&statement(b"z = if 0 then unknown else 3;").unwrap().1)
+            .unwrap();
+        assert_eq!(i.variables[&as_name("x")], 5);
+        assert_eq!(i.variables[&as_name("y")], 0);
+        assert_eq!(i.variables[&as_name("z")], 3);
+    }
+
+    #[test]
+    fn where_bindings_are_evaluated_per_call() {
+        let mut i = Interpreter::new();
+        i.statement(&statement(b"a = 100;").unwrap().1).unwrap();
+        i.statement(&statement(b"f(x) = a * b where a = x + 1, b = a - 3;").unwrap().1)
+            .unwrap();
+        i.statement(&statement(b"y = f(4);").unwrap().1).unwrap();
+        i.statement(&statement(b"z = f(1);").unwrap().1).unwrap();
+        assert_eq!(i.variables[&as_name("y")], 10);
+        assert_eq!(i.variables[&as_name("z")], -2);
+        assert_eq!(i.variables[&as_name("a")], 100);
+    }
+
+    #[test]
+    fn let_bindings_are_scoped() {
+        let mut i = Interpreter::new();
+        i.statement(&statement(b"x = 1;").unwrap().1).unwrap();
+        i.statement(&statement(b"f(n) = let x = n + 1, y = x * 2 in x + y;").unwrap().1)
+            .unwrap();
+        i.statement(&statement(b"a = f(4);").unwrap().1).unwrap();
+        i.statement(&statement(b"b = (let x = 10 in x) + x;").unwrap().1).unwrap();
+        assert_eq!(i.variables[&as_name("a")], 15);
+        assert_eq!(i.variables[&as_name("b")], 11);
+        assert_eq!(i.variables[&as_name("x")], 1);
+        assert_eq!(
+            i.statement(&statement(b"c = (let y = 1 in y) + y;").unwrap().1),
+            Err(Error::UnknownVariable(as_name("y")))
+        );
+    }
+
     fn as_name(s: &str) -> Name {
-        Name(s.to_string())
+        Name::new(s)
     }
 }
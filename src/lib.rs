@@ -1,6 +1,5 @@
 #![feature(slice_patterns)]
 #![feature(box_syntax)]
-#![feature(box_patterns)]
 #![feature(conservative_impl_trait)]
 
 extern crate libc;
@@ -10,45 +9,187 @@ extern crate nom;
 extern crate quickcheck;
 extern crate rand;
 extern crate tempfile;
+extern crate unicode_xid;
 
 pub mod parser;
 pub mod interpreter;
 pub mod compiler;
+pub mod analysis;
+pub mod check;
+pub mod diagnostics;
+pub mod format;
 
+use std::error;
 use std::fmt;
 use std::ffi::CString;
+use std::hash::{Hash, Hasher};
 use std::collections::{HashMap, HashSet};
 use quickcheck::{Arbitrary, Gen};
 
-const RESERVED_NAMES: &'static [&'static str] = &["inputs", "outputs", "if", "match", "_"];
-// FIXME: We really should check characters, not bytes.
-const RESERVED_NAME_BYTES: &'static [u8] = &[b'=', b'(', b')', b'{', b'}', b',', b';'];
+const RESERVED_NAMES: &'static [&'static str] = &[
+    "inputs", "outputs", "if", "then", "else", "match", "mod", "xor", "let", "in", "where", "_",
+];
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
     ParseError(parser::Error),
+    CheckError(check::Error),
     InterpreterError(interpreter::Error),
     CompilerError(compiler::Error),
 }
 
+impl Error {
+    /// Where in the source the error is, if it is about anywhere in particular.
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            Error::ParseError(ref e) => e.span(),
+            Error::CheckError(ref e) => e.span(),
+            Error::InterpreterError(ref e) => e.span(),
+            Error::CompilerError(ref e) => e.span(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::ParseError(ref e) => write!(f, "{}", e),
+            Error::CheckError(ref e) => write!(f, "{}", e),
+            Error::InterpreterError(ref e) => write!(f, "{}", e),
+            Error::CompilerError(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn description(&self) -> &str {
+        match *self {
+            Error::ParseError(_) => "the program couldn't be parsed",
+            Error::CheckError(_) => "the program is inconsistent",
+            Error::InterpreterError(_) => "the program couldn't be run",
+            Error::CompilerError(_) => "the program couldn't be compiled",
+        }
+    }
+}
+
 pub fn interpret(s: &[u8], inputs: &Vec<i64>) -> Result<Vec<i64>, Error> {
-    let program = parser::parse(s).map_err(Error::ParseError)?;
+    let program = parse_and_check(s)?;
     let outputs = interpreter::execute(&program, inputs).map_err(Error::InterpreterError)?;
     return Ok(outputs);
 }
 
-pub fn compile(s: &[u8], emit: compiler::Emit) -> Result<String, Error> {
+/// Parses a program and returns any warnings about it, without running it.
+pub fn analyse(s: &[u8]) -> Result<Vec<analysis::Warning>, Error> {
     let program = parser::parse(s).map_err(Error::ParseError)?;
+    Ok(analysis::warnings(&program))
+}
+
+/// Lays out a program's source canonically, in lines of at most `width` characters where they
+/// can be broken, keeping its comments.
+pub fn format_program(s: &[u8], width: usize) -> Result<String, Error> {
+    format::format_source(s, width).map_err(Error::ParseError)
+}
+
+pub fn compile(s: &[u8], emit: compiler::Emit) -> Result<String, Error> {
+    let program = parse_and_check(s)?;
     let results = unsafe { compiler::compile(&program, emit).map_err(Error::CompilerError)? };
     return Ok(results);
 }
 
+// Parses a program and checks it makes sense, giving the first error otherwise.
+fn parse_and_check(s: &[u8]) -> Result<Program, Error> {
+    let program = parser::parse(s).map_err(Error::ParseError)?;
+    match check::errors(&program).into_iter().next() {
+        Some(error) => Err(Error::CheckError(error)),
+        None => Ok(program),
+    }
+}
+
+/// Where something was written in a program's source: the byte offsets it runs between, and
+/// the line and column (both counted from 1, the column in characters) that it starts at.
+/// Anything that wasn't parsed from source has the default span, at line 0.
+///
+/// Spans are ignored when comparing and hashing, so that a program equals itself parsed
+/// from differently laid out source.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    /// The span between two byte offsets in `source`.
+    pub fn new(source: &[u8], start: usize, end: usize) -> Span {
+        let before = &source[..start];
+        let line_start = before.iter().rposition(|&b| b == b'\n').map_or(0, |i| i + 1);
+        Span {
+            start: start,
+            end: end,
+            line: before.iter().filter(|&&b| b == b'\n').count() + 1,
+            column: String::from_utf8_lossy(&before[line_start..]).chars().count() + 1,
+        }
+    }
+
+    /// The span from the start of this one to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        if self.line == 0 {
+            other
+        } else if other.line == 0 {
+            self
+        } else {
+            Span {
+                end: other.end,
+                ..self
+            }
+        }
+    }
+}
+
+impl PartialEq for Span {
+    fn eq(&self, _: &Span) -> bool {
+        true
+    }
+}
+
+impl Eq for Span {}
+
+impl Hash for Span {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+// The count, with the noun in the singular or plural to suit.
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+// The span, if it is of something written in the source.
+fn located(span: Span) -> Option<Span> {
+    if span.line == 0 {
+        None
+    } else {
+        Some(span)
+    }
+}
+
+/// A name, and where it was written.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Name(pub String);
+pub struct Name(pub String, pub Span);
 
 impl Name {
     pub fn new(s: &str) -> Name {
-        Name(s.to_string())
+        Name(s.to_string(), Span::default())
     }
 
     pub fn cstring(self) -> CString {
@@ -86,7 +227,7 @@ fn arbitrary_name<G: Gen>(g: &mut G, level: usize) -> Name {
             break;
         }
     }
-    Name(name)
+    Name::new(&name)
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -182,8 +323,16 @@ fn arbitrary_program<G: Gen>(
     Program::new(inputs, statements, outputs)
 }
 
+/// Statements, and the doc comment written before each of them.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Statements(pub Vec<Statement>);
+pub struct Statements(pub Vec<Statement>, pub Vec<Option<String>>);
+
+impl Statements {
+    pub fn new(statements: Vec<Statement>) -> Statements {
+        let docs = vec![None; statements.len()];
+        Statements(statements, docs)
+    }
+}
 
 impl fmt::Display for Statements {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -192,7 +341,22 @@ impl fmt::Display for Statements {
             "{}",
             self.0
                 .iter()
-                .map(|s| format!("{}", s))
+                .zip(&self.1)
+                .map(|(s, doc)| match *doc {
+                    Some(ref doc) => {
+                        let doc: Vec<_> = doc.split('\n')
+                            .map(|line| {
+                                if line.is_empty() {
+                                    "///".to_string()
+                                } else {
+                                    format!("/// {}", line)
+                                }
+                            })
+                            .collect();
+                        format!("{}\n{}", doc.join("\n"), s)
+                    }
+                    None => format!("{}", s),
+                })
                 .collect::<Vec<_>>()
                 .join("\n")
         )
@@ -214,28 +378,58 @@ fn arbitrary_statements<G: Gen>(
     let statements_len = 0..g.gen_range(0, size + 1);
     statements_len
         .fold(
-            (Statements(Vec::new()), vars, fns),
+            (Statements::new(Vec::new()), vars, fns),
             |(mut statements, mut vars, mut fns), _| {
                 statements
                     .0
                     .push(arbitrary_statement(g, 1, &mut vars, &mut fns));
+                let doc = if g.gen_weighted_bool(4) {
+                    Some(arbitrary_doc(g))
+                } else {
+                    None
+                };
+                statements.1.push(doc);
                 (statements, vars, fns)
             },
         )
         .0
 }
 
+fn arbitrary_doc<G: Gen>(g: &mut G) -> String {
+    let lines: Vec<String> = (0..g.gen_range(1, 4))
+        .map(|_| {
+            let words: Vec<String> = (0..g.gen_range(0, 4))
+                .map(|_| arbitrary_name(g, 0).0)
+                .collect();
+            words.join(" ")
+        })
+        .collect();
+    lines.join("\n")
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Statement {
-    VarAssignment(Name, Expression),
-    FnDefinition(Name, Vec<Name>, Expression),
+    VarAssignment(Name, Expression, Span),
+    /// The bindings come from a `where` clause, and are evaluated in order before the body on
+    /// every call.
+    FnDefinition(Name, Vec<Name>, Expression, Vec<(Name, Expression)>, Span),
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match *self {
+            Statement::VarAssignment(_, _, span) | Statement::FnDefinition(_, _, _, _, span) => {
+                span
+            }
+        }
+    }
 }
 
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
-            Statement::VarAssignment(ref n, ref e) => write!(f, "{} = {}", n, e)?,
-            Statement::FnDefinition(ref n, ref params, ref e) => {
+            Statement::VarAssignment(ref n, ref e, _) => write!(f, "{} = {}", n, e)?,
+            Statement::FnDefinition(ref n, ref params, ref e, ref bindings, _) => {
                 write!(f, "{}(", n)?;
                 for (i, param) in params.iter().enumerate() {
                     if i > 0 {
@@ -244,12 +438,26 @@ impl fmt::Display for Statement {
                     write!(f, "{}", param)?;
                 }
                 write!(f, ") = {}", e)?;
+                if !bindings.is_empty() {
+                    write!(f, " where ")?;
+                    fmt_bindings(f, bindings)?;
+                }
             }
         }
         write!(f, ";")
     }
 }
 
+fn fmt_bindings(f: &mut fmt::Formatter, bindings: &[(Name, Expression)]) -> fmt::Result {
+    for (i, &(ref name, ref value)) in bindings.iter().enumerate() {
+        if i > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{} = {}", name, value)?;
+    }
+    Ok(())
+}
+
 impl Arbitrary for Statement {
     fn arbitrary<G: Gen>(g: &mut G) -> Statement {
         arbitrary_statement(g, 0, &mut HashSet::new(), &mut HashMap::new())
@@ -269,6 +477,7 @@ fn arbitrary_statement<G: Gen>(
             let statement = Statement::VarAssignment(
                 var_name.clone(),
                 arbitrary_expression(g, level + 1, vars, fns),
+                Span::default(),
             );
             vars.insert(var_name);
             statement
@@ -282,24 +491,55 @@ fn arbitrary_statement<G: Gen>(
             // Removing any previous function by this name prevents the expression from
             // using the previously defined function
             fns.remove(&fn_name);
-            let expr = arbitrary_expression(g, level + 1, &params.clone(), fns);
+            let mut scope = params.clone();
+            let mut bindings = vec![];
+            for _ in 0..g.gen_range(0, 3) {
+                let value = arbitrary_expression(g, level + 1, &scope, fns);
+                let name = Name::arbitrary(g);
+                scope.insert(name.clone());
+                bindings.push((name, value));
+            }
+            let expr = arbitrary_expression(g, level + 1, &scope, fns);
             // FIXME: Identify a nice way to generate recursive function calls without
             // runtime stack overflows.
             fns.insert(fn_name.clone(), params_count);
             // FIXME: Remove or reduce parameters not used in the expression?
-            let statement = Statement::FnDefinition(fn_name, params.into_iter().collect(), expr);
-            statement
+            Statement::FnDefinition(
+                fn_name,
+                params.into_iter().collect(),
+                expr,
+                bindings,
+                Span::default(),
+            )
         }
         _ => unreachable!(),
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 pub enum Expression {
     Operand(Operand),
     Operation(Operator, Box<Expression>, Box<Expression>),
+    /// An expression and where it was written. The parser wraps each operand in one, and it
+    /// is otherwise the same as the expression it wraps.
+    Spanned(Span, Box<Expression>),
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Expression) -> bool {
+        match (self.unspanned(), other.unspanned()) {
+            (&Expression::Operand(ref a), &Expression::Operand(ref b)) => a == b,
+            (
+                &Expression::Operation(operator_a, ref a0, ref a1),
+                &Expression::Operation(operator_b, ref b0, ref b1),
+            ) => operator_a == operator_b && a0 == b0 && a1 == b1,
+            _ => false,
+        }
+    }
 }
 
+impl Eq for Expression {}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Token {
     Operand(Operand),
@@ -310,6 +550,7 @@ impl Expression {
     pub fn tokens(&self) -> Vec<Token> {
         match *self {
             Expression::Operand(ref operand) => vec![Token::Operand(operand.clone())],
+            Expression::Spanned(_, ref expression) => expression.tokens(),
             Expression::Operation(operator, ref exp0, ref exp1) => {
                 let mut tokens = exp0.tokens();
                 tokens.push(Token::Operator(operator));
@@ -318,25 +559,63 @@ impl Expression {
             }
         }
     }
+
+    /// Whether the expression ends with an operand (such as `if`) that would absorb any
+    /// operators written after it, meaning it must be grouped to be used as a left operand.
+    pub fn is_open_ended(&self) -> bool {
+        match *self {
+            Expression::Operand(ref operand) => operand.is_open_ended(),
+            Expression::Operation(operator, _, ref exp1) => match *exp1.unspanned() {
+                Expression::Operation(inner, _, _) if operator.groups_rhs(inner) => false,
+                _ => exp1.is_open_ended(),
+            },
+            Expression::Spanned(_, ref expression) => expression.is_open_ended(),
+        }
+    }
+
+    /// Whether the expression must be grouped to be the left operand of `operator`.
+    pub fn groups_as_lhs_of(&self, operator: Operator) -> bool {
+        match *self.unspanned() {
+            Expression::Operation(inner, _, _) if operator.groups_lhs(inner) => true,
+            Expression::Operand(ref operand) if operator == Operator::Power => {
+                operand.is_negated() || operand.is_open_ended()
+            }
+            _ => self.is_open_ended(),
+        }
+    }
+
+    /// The expression without the spans wrapped around it.
+    pub fn unspanned(&self) -> &Expression {
+        match *self {
+            Expression::Spanned(_, ref expression) => expression.unspanned(),
+            _ => self,
+        }
+    }
+
+    /// Where the expression was written, as far as its spanned parts tell.
+    pub fn span(&self) -> Span {
+        match *self {
+            Expression::Operand(_) => Span::default(),
+            Expression::Operation(_, ref exp0, ref exp1) => exp0.span().to(exp1.span()),
+            Expression::Spanned(span, _) => span,
+        }
+    }
 }
 
 impl fmt::Display for Expression {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Expression::Operand(ref v) => write!(f, "{}", v),
+            Expression::Spanned(_, ref expression) => write!(f, "{}", expression),
             Expression::Operation(ref operator, ref expr1, ref expr2) => {
-                if let &box Expression::Operation(ref inner_operator, _, _) = expr1 {
-                    if inner_operator < operator {
-                        write!(f, "({})", expr1)?;
-                    } else {
-                        write!(f, "{}", expr1)?;
-                    }
+                if expr1.groups_as_lhs_of(*operator) {
+                    write!(f, "({})", expr1)?;
                 } else {
                     write!(f, "{}", expr1)?;
                 }
                 write!(f, " {} ", operator)?;
-                if let &box Expression::Operation(ref inner_operator, _, _) = expr2 {
-                    if inner_operator < operator {
+                if let Expression::Operation(ref inner_operator, _, _) = *expr2.unspanned() {
+                    if operator.groups_rhs(*inner_operator) {
                         write!(f, "({})", expr2)?;
                     } else {
                         write!(f, "{}", expr2)?;
@@ -373,13 +652,11 @@ fn arbitrary_expression<G: Gen>(
             let operator = Operator::arbitrary(g);
             let mut exp0 = arbitrary_expression(g, level + 1, vars, fns);
             let mut exp1 = arbitrary_expression(g, level + 1, vars, fns);
-            if let Expression::Operation(inner_operator, _, _) = exp0.clone() {
-                if inner_operator < operator {
-                    exp0 = Expression::Operand(Operand::Group(box exp0));
-                }
+            if exp0.groups_as_lhs_of(operator) {
+                exp0 = Expression::Operand(Operand::Group(box exp0));
             }
             if let Expression::Operation(inner_operator, _, _) = exp1.clone() {
-                if inner_operator < operator {
+                if operator.groups_rhs(inner_operator) {
                     exp1 = Expression::Operand(Operand::Group(box exp1));
                 }
             }
@@ -396,6 +673,32 @@ pub enum Operand {
     VarSubstitution(Name),
     FnApplication(Name, Vec<Expression>),
     Match(Match),
+    If(Box<Expression>, Box<Expression>, Box<Expression>),
+    Not(Box<Operand>),
+    Negate(Box<Operand>),
+    /// Bindings are evaluated in order, each visible to those after it and to the body.
+    Let(Vec<(Name, Expression)>, Box<Expression>),
+}
+
+impl Operand {
+    pub fn is_open_ended(&self) -> bool {
+        match *self {
+            Operand::If(..) | Operand::Let(..) => true,
+            Operand::Not(ref operand) | Operand::Negate(ref operand) => operand.is_open_ended(),
+            _ => false,
+        }
+    }
+
+    /// Whether the operand starts with a `-` that would take in a `^` written after it, as
+    /// `-x ^ 2` is `-(x ^ 2)`.
+    pub fn is_negated(&self) -> bool {
+        match *self {
+            Operand::I64(n) => n < 0,
+            Operand::Negate(_) => true,
+            Operand::Not(ref operand) => operand.is_negated(),
+            _ => false,
+        }
+    }
 }
 
 impl fmt::Display for Operand {
@@ -415,6 +718,16 @@ impl fmt::Display for Operand {
                 write!(f, ")")
             }
             Operand::Match(ref match_) => write!(f, "{}", match_),
+            Operand::If(ref condition, ref then, ref else_) => {
+                write!(f, "if {} then {} else {}", condition, then, else_)
+            }
+            Operand::Not(ref operand) => write!(f, "!{}", operand),
+            Operand::Negate(ref operand) => write!(f, "-{}", operand),
+            Operand::Let(ref bindings, ref body) => {
+                write!(f, "let ")?;
+                fmt_bindings(f, bindings)?;
+                write!(f, " in {}", body)
+            }
         }
     }
 }
@@ -436,7 +749,7 @@ fn arbitrary_operand<G: Gen>(
     if size <= 1 {
         return Operand::I64(i64::arbitrary(g));
     }
-    match g.gen_range(0, 4) {
+    match g.gen_range(0, 8) {
         0 => Operand::I64(i64::arbitrary(g)),
         1 => g.choose(vars.iter().collect::<Vec<_>>().as_slice())
             .map(|var_name| Operand::VarSubstitution(var_name.clone().clone()))
@@ -452,36 +765,84 @@ fn arbitrary_operand<G: Gen>(
             })
             .unwrap_or_else(|| Operand::I64(i64::arbitrary(g))),
         3 => Operand::Match(arbitrary_match(g, level + 1, vars, fns)),
+        4 => Operand::If(
+            box arbitrary_expression(g, level + 1, vars, fns),
+            box arbitrary_expression(g, level + 1, vars, fns),
+            box arbitrary_expression(g, level + 1, vars, fns),
+        ),
+        5 => Operand::Not(box arbitrary_operand(g, level + 1, vars, fns)),
+        // `-` directly followed by a literal reads back as a negative literal.
+        6 => match arbitrary_operand(g, level + 1, vars, fns) {
+            literal @ Operand::I64(_) => {
+                Operand::Negate(box Operand::Group(box Expression::Operand(literal)))
+            }
+            operand => Operand::Negate(box operand),
+        },
+        7 => {
+            let mut scope = vars.clone();
+            let mut bindings = vec![];
+            for _ in 0..g.gen_range(1, 3) {
+                let value = arbitrary_expression(g, level + 1, &scope, fns);
+                let name = arbitrary_name(g, level + 1);
+                scope.insert(name.clone());
+                bindings.push((name, value));
+            }
+            Operand::Let(bindings, box arbitrary_expression(g, level + 1, &scope, fns))
+        }
         _ => unreachable!(),
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Match {
-    with: Box<Expression>,
-    clauses: Vec<(Matcher, Expression)>,
+    /// Several values are matched at once by `Matcher::Tuple`s of the same length.
+    with: Vec<Expression>,
+    /// Each clause's optional guard must also be nonzero for the clause to be taken.
+    clauses: Vec<(Matcher, Option<Expression>, Expression)>,
     default: Box<Expression>,
+    span: Span,
 }
 
 impl Match {
     pub fn new(
-        with: Expression,
-        matchers: Vec<(Matcher, Expression)>,
+        with: Vec<Expression>,
+        matchers: Vec<(Matcher, Option<Expression>, Expression)>,
         default: Expression,
     ) -> Match {
         Match {
-            with: box with,
+            with: with,
             clauses: matchers,
             default: box default,
+            span: Span::default(),
         }
     }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
 }
 
 impl fmt::Display for Match {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "match {} {{ ", self.with)?;
-        for &(ref match_, ref expr) in &self.clauses {
-            write!(f, "{} => {}, ", match_, expr)?;
+        if self.with.len() == 1 {
+            write!(f, "match {} {{ ", self.with[0])?;
+        } else {
+            write!(
+                f,
+                "match ({}) {{ ",
+                self.with
+                    .iter()
+                    .map(|e| format!("{}", e))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )?;
+        }
+        for &(ref match_, ref guard, ref expr) in &self.clauses {
+            write!(f, "{}", match_)?;
+            if let &Some(ref guard) = guard {
+                write!(f, " if {}", guard)?;
+            }
+            write!(f, " => {}, ", expr)?;
         }
         write!(f, "_ => {}, ", self.default)?;
         write!(f, "}}")
@@ -501,9 +862,24 @@ fn arbitrary_match<G: Gen>(
     fns: &HashMap<Name, usize>,
 ) -> Match {
     let size = g.size().saturating_sub(level);
+    let arity = match g.gen_range(0, 4) {
+        0 => g.gen_range(2, 4),
+        _ => 1,
+    };
     let mut matchers = vec![];
     for _ in 0..(size + 1) {
-        let matcher = arbitrary_matcher(g, level + 1, vars, fns);
+        let matcher = match g.gen_range(0, 4) {
+            0 => Matcher::Wildcard,
+            _ if arity > 1 => Matcher::Tuple(
+                (0..arity)
+                    .map(|_| match g.gen_range(0, 3) {
+                        0 => Matcher::Wildcard,
+                        _ => arbitrary_binding_matcher(g, level + 1, vars, fns),
+                    })
+                    .collect(),
+            ),
+            _ => arbitrary_binding_matcher(g, level + 1, vars, fns),
+        };
         if !matchers.contains(&matcher) {
             matchers.push(matcher);
         }
@@ -511,24 +887,118 @@ fn arbitrary_match<G: Gen>(
     let clauses = matchers
         .into_iter()
         .map(|matcher| {
-            let expression = arbitrary_expression(g, level + 1, vars, fns);
-            (matcher, expression)
+            let mut scope = vars.clone();
+            scope.extend(matcher.bindings().into_iter().map(|(_, name)| name));
+            // An unguarded wildcard would read back as the default clause.
+            let guard = if matcher == Matcher::Wildcard || g.gen() {
+                Some(arbitrary_expression(g, level + 1, &scope, fns))
+            } else {
+                None
+            };
+            let expression = arbitrary_expression(g, level + 1, &scope, fns);
+            (matcher, guard, expression)
         })
         .collect();
-    let with = arbitrary_expression(g, level + 1, vars, fns);
+    let with = (0..arity)
+        .map(|_| arbitrary_expression(g, level + 1, vars, fns))
+        .collect();
     let default = arbitrary_expression(g, level + 1, vars, fns);
-    Match::new(with, clauses, default)
+    let mut match_ = Match::new(with, clauses, default);
+    // Avoid clauses the analysis would warn about.
+    for i in analysis::unreachable_clauses(&match_).into_iter().rev() {
+        match_.clauses.remove(i);
+    }
+    while !analysis::is_default_reachable(&match_) {
+        match_.clauses.pop();
+    }
+    match_
+}
+
+fn arbitrary_binding_matcher<G: Gen>(
+    g: &mut G,
+    level: usize,
+    vars: &HashSet<Name>,
+    fns: &HashMap<Name, usize>,
+) -> Matcher {
+    match g.gen_range(0, 4) {
+        0 => Matcher::Binding(arbitrary_name(g, level), box Matcher::Wildcard),
+        1 => Matcher::Binding(
+            arbitrary_name(g, level),
+            box arbitrary_matcher(g, level, vars, fns),
+        ),
+        _ => arbitrary_matcher(g, level, vars, fns),
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Matcher {
     Value(Expression),
+    Range {
+        start: Expression,
+        end: Expression,
+        inclusive: bool,
+    },
+    Alternatives(Vec<Matcher>),
+    /// Matches anything. Only written with a guard, as an unguarded `_` is the default clause.
+    Wildcard,
+    /// Binds the scrutinee to a name in the clause's guard and body. Written `name @ matcher`,
+    /// or `let name` when wrapping `Wildcard`. Only used for a clause's whole matcher, or for
+    /// the elements of a `Tuple`.
+    Binding(Name, Box<Matcher>),
+    /// Matches each of several values, as in `(0, _)`. Only used for a clause's whole matcher.
+    Tuple(Vec<Matcher>),
+}
+
+impl Matcher {
+    /// The names bound by this matcher, each with the position of the value it is bound to.
+    pub fn bindings(&self) -> Vec<(usize, Name)> {
+        match *self {
+            Matcher::Binding(ref name, _) => vec![(0, name.clone())],
+            Matcher::Tuple(ref matchers) => matchers
+                .iter()
+                .enumerate()
+                .filter_map(|(i, matcher)| match *matcher {
+                    Matcher::Binding(ref name, _) => Some((i, name.clone())),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        }
+    }
 }
 
 impl fmt::Display for Matcher {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             &Matcher::Value(ref expression) => write!(f, "{}", expression),
+            &Matcher::Range {
+                ref start,
+                ref end,
+                inclusive,
+            } => write!(f, "{}{}{}", start, if inclusive { "..=" } else { ".." }, end),
+            &Matcher::Alternatives(ref matchers) => write!(
+                f,
+                "{}",
+                matchers
+                    .iter()
+                    .map(|m| format!("{}", m))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            ),
+            &Matcher::Wildcard => write!(f, "_"),
+            &Matcher::Binding(ref name, ref matcher) if **matcher == Matcher::Wildcard => {
+                write!(f, "let {}", name)
+            }
+            &Matcher::Binding(ref name, ref matcher) => write!(f, "{} @ {}", name, matcher),
+            &Matcher::Tuple(ref matchers) => write!(
+                f,
+                "({})",
+                matchers
+                    .iter()
+                    .map(|m| format!("{}", m))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
         }
     }
 }
@@ -545,15 +1015,137 @@ fn arbitrary_matcher<G: Gen>(
     vars: &HashSet<Name>,
     fns: &HashMap<Name, usize>,
 ) -> Matcher {
-    Matcher::Value(arbitrary_expression(g, level + 1, vars, fns))
+    let size = g.size().saturating_sub(level);
+    // Terminate alternatives of sufficient depth.
+    if size <= 1 {
+        return arbitrary_single_matcher(g, level + 1, vars, fns);
+    }
+    match g.gen_range(0, 3) {
+        0 | 1 => arbitrary_single_matcher(g, level + 1, vars, fns),
+        2 => Matcher::Alternatives(
+            (0..g.gen_range(2, size + 2))
+                .map(|_| arbitrary_single_matcher(g, level + 1, vars, fns))
+                .collect(),
+        ),
+        _ => unreachable!(),
+    }
+}
+
+// Alternatives are flattened when parsed, so they never contain one another.
+fn arbitrary_single_matcher<G: Gen>(
+    g: &mut G,
+    level: usize,
+    vars: &HashSet<Name>,
+    fns: &HashMap<Name, usize>,
+) -> Matcher {
+    match g.gen_range(0, 2) {
+        0 => Matcher::Value(arbitrary_pattern_expression(g, level + 1, vars, fns)),
+        1 => Matcher::Range {
+            start: arbitrary_pattern_expression(g, level + 1, vars, fns),
+            end: arbitrary_pattern_expression(g, level + 1, vars, fns),
+            inclusive: g.gen(),
+        },
+        _ => unreachable!(),
+    }
+}
+
+// Within a pattern `|` separates alternatives, so bitwise ors must be grouped.
+fn arbitrary_pattern_expression<G: Gen>(
+    g: &mut G,
+    level: usize,
+    vars: &HashSet<Name>,
+    fns: &HashMap<Name, usize>,
+) -> Expression {
+    let expression = arbitrary_expression(g, level, vars, fns);
+    if exposes_bit_or(&expression) || expression.is_open_ended() {
+        Expression::Operand(Operand::Group(box expression))
+    } else {
+        expression
+    }
+}
+
+fn exposes_bit_or(expression: &Expression) -> bool {
+    match *expression {
+        Expression::Operation(Operator::BitOr, _, _) => true,
+        Expression::Operation(_, ref exp1, ref exp2) => {
+            exposes_bit_or(exp1) || exposes_bit_or(exp2)
+        }
+        Expression::Operand(Operand::Not(ref operand))
+        | Expression::Operand(Operand::Negate(ref operand)) => {
+            exposes_bit_or(&Expression::Operand(*operand.clone()))
+        }
+        Expression::Operand(Operand::If(_, _, ref else_)) => exposes_bit_or(else_),
+        Expression::Operand(Operand::Let(_, ref body)) => exposes_bit_or(body),
+        Expression::Operand(_) => false,
+        Expression::Spanned(_, ref expression) => exposes_bit_or(expression),
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Operator {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    ShiftLeft,
+    ShiftRight,
     Subtract,
     Add,
+    Remainder,
+    Modulo,
     Divide,
     Multiply,
+    Power,
+}
+
+impl Operator {
+    pub fn is_right_associative(&self) -> bool {
+        match *self {
+            Operator::Power => true,
+            _ => false,
+        }
+    }
+
+    /// How tightly the operator binds its operands, following C for the operators it shares.
+    /// Operators of the same precedence, such as `==` and `!=`, associate with each other as they
+    /// do with themselves.
+    pub fn precedence(&self) -> usize {
+        match *self {
+            Operator::Or => 0,
+            Operator::And => 1,
+            Operator::BitOr => 2,
+            Operator::BitXor => 3,
+            Operator::BitAnd => 4,
+            Operator::Equal | Operator::NotEqual => 5,
+            Operator::LessThan
+            | Operator::LessThanOrEqual
+            | Operator::GreaterThan
+            | Operator::GreaterThanOrEqual => 6,
+            Operator::ShiftLeft | Operator::ShiftRight => 7,
+            Operator::Subtract | Operator::Add => 8,
+            Operator::Remainder | Operator::Modulo | Operator::Divide | Operator::Multiply => 9,
+            Operator::Power => 10,
+        }
+    }
+
+    /// Whether an operation using `inner` needs grouping to be the left operand of `self`.
+    pub fn groups_lhs(&self, inner: Operator) -> bool {
+        inner.precedence() < self.precedence()
+            || (inner.precedence() == self.precedence() && self.is_right_associative())
+    }
+
+    /// Whether an operation using `inner` needs grouping to be the right operand of `self`.
+    pub fn groups_rhs(&self, inner: Operator) -> bool {
+        inner.precedence() < self.precedence()
+            || (inner.precedence() == self.precedence() && !self.is_right_associative())
+    }
 }
 
 impl fmt::Display for Operator {
@@ -562,10 +1154,26 @@ impl fmt::Display for Operator {
             f,
             "{}",
             match *self {
+                Operator::Or => "||",
+                Operator::And => "&&",
+                Operator::BitOr => "|",
+                Operator::BitXor => "xor",
+                Operator::BitAnd => "&",
+                Operator::Equal => "==",
+                Operator::NotEqual => "!=",
+                Operator::LessThan => "<",
+                Operator::LessThanOrEqual => "<=",
+                Operator::GreaterThan => ">",
+                Operator::GreaterThanOrEqual => ">=",
+                Operator::ShiftLeft => "<<",
+                Operator::ShiftRight => ">>",
                 Operator::Subtract => "-",
                 Operator::Add => "+",
+                Operator::Remainder => "%",
+                Operator::Modulo => "mod",
                 Operator::Divide => "/",
                 Operator::Multiply => "*",
+                Operator::Power => "^",
             }
         )
     }
@@ -573,11 +1181,27 @@ impl fmt::Display for Operator {
 
 impl Arbitrary for Operator {
     fn arbitrary<G: Gen>(g: &mut G) -> Operator {
-        match g.gen_range(0, 4) {
+        match g.gen_range(0, 20) {
             0 => Operator::Subtract,
             1 => Operator::Add,
             2 => Operator::Divide,
             3 => Operator::Multiply,
+            4 => Operator::Equal,
+            5 => Operator::NotEqual,
+            6 => Operator::LessThan,
+            7 => Operator::LessThanOrEqual,
+            8 => Operator::GreaterThan,
+            9 => Operator::GreaterThanOrEqual,
+            10 => Operator::Or,
+            11 => Operator::And,
+            12 => Operator::Remainder,
+            13 => Operator::Modulo,
+            14 => Operator::Power,
+            15 => Operator::BitOr,
+            16 => Operator::BitXor,
+            17 => Operator::BitAnd,
+            18 => Operator::ShiftLeft,
+            19 => Operator::ShiftRight,
             _ => unreachable!(),
         }
     }
@@ -714,4 +1338,133 @@ mod tests {
             qc.quickcheck(interprets_and_compiles_the_same_property as fn(Testcase) -> bool);
         }
     }
+
+    #[test]
+    fn remainders_interpret_and_compile_the_same() {
+        let program = parser::parse(
+            b"inputs a, b; r = a % b; m = a mod b; outputs r, m;",
+        ).unwrap();
+        let values = vec![i64::min_value(), -7, -2, -1, 0, 1, 2, 7, i64::max_value()];
+        for &a in &values {
+            for &b in &values {
+                assert!(interprets_and_compiles_the_same_property(Testcase {
+                    program: program.clone(),
+                    inputs: vec![a, b],
+                }));
+            }
+        }
+
+        // Remainders by zero leave the numerator unchanged, even a negative one for `mod`.
+        let by_zero = Testcase {
+            program: program,
+            inputs: vec![-7, 0],
+        };
+        assert_eq!(interpret_testcase(by_zero.clone()), Some(vec![-7, -7]));
+        assert_eq!(compile_and_run_testcase(by_zero), Some(vec![-7, -7]));
+    }
+
+    #[test]
+    fn powers_interpret_and_compile_the_same() {
+        let program = parser::parse(b"inputs a, b; p = a ^ b; outputs p;").unwrap();
+        let values = vec![i64::min_value(), -3, -2, -1, 0, 1, 2, 3, 62, 63, 64, i64::max_value()];
+        for &a in &values {
+            for &b in &values {
+                assert!(interprets_and_compiles_the_same_property(Testcase {
+                    program: program.clone(),
+                    inputs: vec![a, b],
+                }));
+            }
+        }
+    }
+
+    #[test]
+    fn bitwise_operators_interpret_and_compile_the_same() {
+        let program = parser::parse(
+            b"inputs a, b; o = a | b; x = a xor b; n = a & b; l = a << b; r = a >> b; \
+              outputs o, x, n, l, r;",
+        ).unwrap();
+        let values = vec![i64::min_value(), -65, -64, -1, 0, 1, 5, 63, 64, 65, i64::max_value()];
+        for &a in &values {
+            for &b in &values {
+                assert!(interprets_and_compiles_the_same_property(Testcase {
+                    program: program.clone(),
+                    inputs: vec![a, b],
+                }));
+            }
+        }
+    }
+
+    #[test]
+    fn negation_interprets_and_compiles_the_same() {
+        let program = parser::parse(b"inputs a; n = -a; p = +a; outputs n, p;").unwrap();
+        for &a in &[i64::min_value(), -1, 0, 1, i64::max_value()] {
+            assert!(interprets_and_compiles_the_same_property(Testcase {
+                program: program.clone(),
+                inputs: vec![a],
+            }));
+        }
+    }
+
+    #[test]
+    fn piecewise_functions_interpret_and_compile_the_same() {
+        let program = parser::parse(PIECEWISE_PROGRAM).unwrap();
+        for a in -3..6 {
+            for b in -3..6 {
+                assert!(interprets_and_compiles_the_same_property(Testcase {
+                    program: program.clone(),
+                    inputs: vec![a, b],
+                }));
+            }
+        }
+        assert_eq!(
+            interpreter::execute(&program, &vec![5, 3]),
+            Ok(vec![120, 9, 1])
+        );
+    }
+
+    const PIECEWISE_PROGRAM: &'static [u8] = b"inputs a, b;
+fact(0) = 1;
+fact(n) = n * fact(n - 1);
+ack(0, n) = n + 1;
+ack(m, 0) = ack(m - 1, 1);
+ack(m, n) = ack(m - 1, ack(m, n - 1));
+sign(0) = 0;
+sign(1..=9223372036854775807) = 1;
+x = fact(a mod 10);
+y = ack(a mod 3, b mod 4);
+s = sign(b);
+outputs x, y, s;
+";
+
+    #[test]
+    fn unicode_names_interpret_and_compile_the_same() {
+        // `_U1` would be mistaken for the mangled name of `1` if it weren't mangled itself.
+        let program = parser::parse(
+            "inputs \u{3b1};
+\u{394}(x) = x * 2;
+f_\u{3b2}(x) = x + 1;
+_U1(x) = x - 1;
+y = \u{394}(\u{3b1}) + f_\u{3b2}(\u{3b1}) + _U1(\u{3b1});
+outputs y;
+".as_bytes(),
+        ).unwrap();
+        for a in -3..3 {
+            assert!(interprets_and_compiles_the_same_property(Testcase {
+                program: program.clone(),
+                inputs: vec![a],
+            }));
+        }
+        assert_eq!(interpreter::execute(&program, &vec![3]), Ok(vec![12]));
+    }
+
+    #[test]
+    fn e_math_v2_interprets_and_compiles_the_same() {
+        let program = parser::parse(include_bytes!("../e.math.v2")).unwrap();
+        for i in -1..11 {
+            assert!(interprets_and_compiles_the_same_property(Testcase {
+                program: program.clone(),
+                inputs: vec![i],
+            }));
+        }
+    }
 }
@@ -0,0 +1,96 @@
+use nom::IResult;
+
+/// Skips whitespace and comments, which run from `#` or `//` to the end of the line, or from
+/// `/*` to `*/`. Used as the separator for `ws!`.
+pub fn blank(input: &[u8]) -> IResult<&[u8], &[u8]> {
+    let rest = skip_blank(input).0;
+    IResult::Done(rest, &input[..input.len() - rest.len()])
+}
+
+/// Like `blank`, but gives the lines of the doc comments (`///` or `##`) directly before the
+/// rest of the input, without the comment markers and one space after them.
+pub fn doc_comment(input: &[u8]) -> IResult<&[u8], Option<String>> {
+    let (rest, lines) = skip_blank(input);
+    let doc = if lines.is_empty() {
+        None
+    } else {
+        let lines: Vec<_> = lines
+            .into_iter()
+            .map(|line| String::from_utf8_lossy(line).into_owned())
+            .collect();
+        Some(lines.join("\n"))
+    };
+    IResult::Done(rest, doc)
+}
+
+fn skip_blank(mut input: &[u8]) -> (&[u8], Vec<&[u8]>) {
+    let mut doc = vec![];
+    loop {
+        let spaces = input
+            .iter()
+            .take_while(|&&b| b == b' ' || b == b'\t' || b == b'\r' || b == b'\n')
+            .count();
+        input = &input[spaces..];
+        if input.starts_with(b"///") || input.starts_with(b"##") {
+            let marker = if input.starts_with(b"///") { 3 } else { 2 };
+            let (line, rest) = split_line(&input[marker..]);
+            doc.push(if line.starts_with(b" ") { &line[1..] } else { line });
+            input = rest;
+        } else if input.starts_with(b"//") || input.starts_with(b"#") {
+            input = split_line(input).1;
+            doc.clear();
+        } else if input.starts_with(b"/*") {
+            match input.windows(2).skip(2).position(|w| w == b"*/") {
+                Some(end) => input = &input[end + 4..],
+                // Left for the parser to reject.
+                None => return (input, doc),
+            }
+            doc.clear();
+        } else {
+            return (input, doc);
+        }
+    }
+}
+
+// Splits off the rest of the line, without its line ending.
+fn split_line(input: &[u8]) -> (&[u8], &[u8]) {
+    let end = input.iter().position(|&b| b == b'\n').unwrap_or(input.len());
+    let line = &input[..end];
+    let line = if line.ends_with(b"\r") {
+        &line[..end - 1]
+    } else {
+        line
+    };
+    (line, &input[end..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blank_test() {
+        assert_eq!(blank(b" \n\tx"), IResult::Done(&b"x"[..], &b" \n\t"[..]));
+        assert_eq!(
+            blank(b"# one\n// two\r\n  /* three\n */ x"),
+            IResult::Done(&b"x"[..], &b"# one\n// two\r\n  /* three\n */ "[..])
+        );
+        assert_eq!(blank(b"/* open x"), IResult::Done(&b"/* open x"[..], &b""[..]));
+        assert_eq!(blank(b"x # y"), IResult::Done(&b"x # y"[..], &b""[..]));
+        assert_eq!(blank(b"/ x"), IResult::Done(&b"/ x"[..], &b""[..]));
+    }
+
+    #[test]
+    fn doc_comment_test() {
+        assert_eq!(doc_comment(b"  x"), IResult::Done(&b"x"[..], None));
+        assert_eq!(
+            doc_comment(b"/// One.\n///\n##  Two.\r\nx"),
+            IResult::Done(&b"x"[..], Some("One.\n\n Two.".to_string()))
+        );
+        // Only the doc comments directly before count.
+        assert_eq!(
+            doc_comment(b"/// One.\n# Two.\n/// Three.\n\nx"),
+            IResult::Done(&b"x"[..], Some("Three.".to_string()))
+        );
+    }
+}
@@ -1,16 +1,27 @@
 use super::*;
 use self::shunting_yard::*;
 use std::str;
-use nom::is_digit;
+use std::cell::RefCell;
+use nom::{is_digit, is_hex_digit, ErrorKind};
+
+thread_local! {
+    static OUT_OF_RANGE_LITERAL: RefCell<Option<(String, Span)>> = RefCell::new(None);
+}
+
+/// Takes the last integer literal found too large for an `i64`, for `parse` to report in
+/// place of nom's error, which can't say what went wrong.
+pub fn take_out_of_range_literal() -> Option<(String, Span)> {
+    OUT_OF_RANGE_LITERAL.with(|literal| literal.borrow_mut().take())
+}
 
 named!(pub expressions<&[u8], Vec<Expression>>,
   separated_list!(ws!(tag!(",")), call!(expression)));
 
 named!(pub expression<&[u8], Expression>,
     do_parse!(
-        first_operand: ws!(call!(operand)) >>
+        first_operand: ws!(call!(spanned_operand)) >>
         shunting_yard: fold_many0!(
-            pair!(ws!(call!(operator)), ws!(call!(operand))),
+            pair!(ws!(call!(operator)), ws!(call!(spanned_operand))),
             ShuntingYard::new(first_operand),
             |mut shunting_yard: ShuntingYard, (operator, operand)| {
                 shunting_yard.push(operator, operand);
@@ -19,27 +30,143 @@ named!(pub expression<&[u8], Expression>,
         ) >>
         (shunting_yard.into_expression())));
 
+// Within patterns `|` separates alternatives instead of being a bitwise or.
+named!(pattern_expression<&[u8], Expression>,
+    do_parse!(
+        first_operand: ws!(call!(spanned_operand)) >>
+        shunting_yard: fold_many0!(
+            pair!(ws!(call!(pattern_operator)), ws!(call!(spanned_operand))),
+            ShuntingYard::new(first_operand),
+            |mut shunting_yard: ShuntingYard, (operator, operand)| {
+                shunting_yard.push(operator, operand);
+                shunting_yard
+            }
+        ) >>
+        (shunting_yard.into_expression())));
+
+named!(spanned_operand<&[u8], Expression>,
+  map!(
+    call!(with_span, operand),
+    |(operand, span)| Expression::Spanned(span, box Expression::Operand(operand))));
+
+named!(pattern_operator<&[u8], Operator>,
+  map_opt!(call!(operator), |o| if o == Operator::BitOr { None } else { Some(o) }));
+
 named!(operator<&[u8], Operator>,
-  map!(one_of!("+-*/"), |o| match o {
-    '+' => Operator::Add,
-    '-' => Operator::Subtract,
-    '*' => Operator::Multiply,
-    '/' => Operator::Divide,
-    _ => unreachable!()
-  }));
+  alt_complete!(
+    map!(tag!("||"), |_| Operator::Or) |
+    map!(tag!("&&"), |_| Operator::And) |
+    map!(tag!("<<"), |_| Operator::ShiftLeft) |
+    map!(tag!(">>"), |_| Operator::ShiftRight) |
+    map!(tag!("=="), |_| Operator::Equal) |
+    map!(tag!("!="), |_| Operator::NotEqual) |
+    map!(tag!("<="), |_| Operator::LessThanOrEqual) |
+    map!(tag!(">="), |_| Operator::GreaterThanOrEqual) |
+    map!(tag!("<"), |_| Operator::LessThan) |
+    map!(tag!(">"), |_| Operator::GreaterThan) |
+    map!(call!(keyword, "mod"), |_| Operator::Modulo) |
+    map!(call!(keyword, "xor"), |_| Operator::BitXor) |
+    map!(one_of!("+-*/%^&|"), |o| match o {
+      '+' => Operator::Add,
+      '-' => Operator::Subtract,
+      '*' => Operator::Multiply,
+      '/' => Operator::Divide,
+      '%' => Operator::Remainder,
+      '^' => Operator::Power,
+      '&' => Operator::BitAnd,
+      '|' => Operator::BitOr,
+      _ => unreachable!()
+    })));
 
 named!(operand<&[u8], Operand>,
   alt_complete!(
     map!(i64, Operand::I64) |
     map!(group, |inner_expression| Operand::Group(box inner_expression)) |
+    map!(not, |operand| Operand::Not(box operand)) |
+    map!(negate, |operand| Operand::Negate(box operand)) |
+    call!(plus) |
     map!(variable_substitution, Operand::VarSubstitution) |
     map!(function_application, |t| Operand::FnApplication(t.0, t.1)) |
-    map!(match_, |m| Operand::Match(m))));
+    map!(match_, |m| Operand::Match(m)) |
+    map!(if_, |(condition, then, else_)| Operand::If(box condition, box then, box else_)) |
+    map!(let_, |(bindings, body)| Operand::Let(bindings, box body))));
 
-named!(i64<&[u8], i64>,
-  map!(
-    take_while1!(|b: u8| is_digit(b) || b == b'-'),
-    |i| str::from_utf8(i).unwrap().parse().unwrap()));
+// Integer literals, such as `-12`, `1_000`, `0xFF` or `0b1010`.
+fn i64(input: &[u8]) -> IResult<&[u8], i64> {
+    let (rest, (sign, (radix, digits))) = try_parse!(input, literal);
+    // `-` applies to a whole power, so `-2 ^ 2` is left for `negate`.
+    if sign == "-" && blank(rest).unwrap().0.starts_with(b"^") {
+        return IResult::Error(ErrorKind::Custom(0));
+    }
+    let digits: String = digits
+        .iter()
+        .filter(|&&b| b != b'_')
+        .map(|&b| b as char)
+        .collect();
+    match i64::from_str_radix(&format!("{}{}", sign, digits), radix) {
+        Ok(value) => IResult::Done(rest, value),
+        Err(_) => {
+            let text = String::from_utf8_lossy(&input[..input.len() - rest.len()]).into_owned();
+            OUT_OF_RANGE_LITERAL.with(|literal| {
+                *literal.borrow_mut() = Some((text, span(input, rest)))
+            });
+            IResult::Error(ErrorKind::Custom(0))
+        }
+    }
+}
+
+named!(literal<&[u8], (&str, (u32, &[u8]))>,
+  pair!(
+    map!(opt!(tag!("-")), |sign: Option<&[u8]>| if sign.is_some() { "-" } else { "" }),
+    alt_complete!(
+      map!(preceded!(tag!("0x"), call!(digits, is_hex_digit)), |digits| (16, digits)) |
+      map!(preceded!(tag!("0b"), call!(digits, is_binary_digit)), |digits| (2, digits)) |
+      map!(call!(digits, is_digit), |digits| (10, digits))
+    )
+  ));
+
+// Digits, possibly separated by underscores.
+fn digits<F: Fn(u8) -> bool>(input: &[u8], is_digit: F) -> IResult<&[u8], &[u8]> {
+    recognize!(
+        input,
+        pair!(
+            verify!(take!(1), |d: &[u8]| is_digit(d[0])),
+            take_while!(|b| is_digit(b) || b == b'_')
+        )
+    )
+}
+
+fn is_binary_digit(b: u8) -> bool {
+    b == b'0' || b == b'1'
+}
+
+named!(not<&[u8], Operand>,
+  preceded!(tag!("!"), call!(operand)));
+
+// As is usual in mathematics, `-x ^ 2` is `-(x ^ 2)`.
+named!(negate<&[u8], Operand>,
+  do_parse!(
+    tag!("-") >>
+    base: call!(with_span, operand) >>
+    exponents: many0!(complete!(preceded!(ws!(tag!("^")), ws!(call!(spanned_operand))))) >>
+    (power(base, exponents))));
+
+// The base raised to the exponents, which associate to the right.
+fn power((base, span): (Operand, Span), mut exponents: Vec<Expression>) -> Operand {
+    let last = match exponents.pop() {
+        Some(last) => last,
+        None => return base,
+    };
+    exponents.insert(0, Expression::Spanned(span, box Expression::Operand(base)));
+    let power = exponents.into_iter().rev().fold(last, |exponent, operand| {
+        Expression::Operation(Operator::Power, box operand, box exponent)
+    });
+    Operand::Group(box power)
+}
+
+// Unary plus is the identity, so it leaves no trace in the AST.
+named!(plus<&[u8], Operand>,
+  preceded!(tag!("+"), call!(operand)));
 
 named!(group<&[u8], Expression>,
   delimited!(
@@ -63,18 +190,61 @@ named!(function_application<&[u8], (Name, Vec<Expression>)>,
     (name, expressions)));
 
 named!(match_<&[u8], Match>,
-  map!(
-    do_parse!(
-      ws!(tag!("match ")) >>
-      with: call!(expression) >>
-      ws!(tag!("{")) >>
-      clauses: call!(match_clauses) >>
-      opt!(ws!(tag!(","))) >>
-      ws!(tag!("}")) >>
-      (with, clauses)),
-    |(with, (matchers, default))| Match::new(with, matchers, default)));
-
-named!(match_clauses<&[u8], (Vec<(Matcher, Expression)>, Expression)>,
+  map_opt!(
+    call!(with_span, match_parts),
+    |((with, (matchers, default)), span)| checked_match(with, matchers, default, span)));
+
+// The clauses of a match, and its default.
+type Clauses = (Vec<(Matcher, Option<Expression>, Expression)>, Expression);
+
+named!(match_parts<&[u8], (Vec<Expression>, Clauses)>,
+  do_parse!(
+    ws!(call!(keyword, "match")) >>
+    with: alt_complete!(
+      call!(tuple_expressions) |
+      map!(call!(expression), |expression| vec![expression])
+    ) >>
+    ws!(tag!("{")) >>
+    clauses: call!(match_clauses) >>
+    opt!(ws!(tag!(","))) >>
+    ws!(tag!("}")) >>
+    (with, clauses)));
+
+named!(tuple_expressions<&[u8], Vec<Expression>>,
+  map_opt!(
+    delimited!(ws!(tag!("(")), call!(expressions), ws!(tag!(")"))),
+    |expressions: Vec<Expression>| if expressions.len() > 1 {
+        Some(expressions)
+    } else {
+        None
+    }));
+
+named!(if_<&[u8], (Expression, Expression, Expression)>,
+  do_parse!(
+    ws!(call!(keyword, "if")) >>
+    condition: call!(expression) >>
+    ws!(call!(keyword, "then")) >>
+    then: call!(expression) >>
+    ws!(call!(keyword, "else")) >>
+    else_: call!(expression) >>
+    (condition, then, else_)));
+
+named!(let_<&[u8], (Vec<(Name, Expression)>, Expression)>,
+  do_parse!(
+    ws!(call!(keyword, "let")) >>
+    bindings: separated_nonempty_list!(ws!(tag!(",")), call!(let_binding)) >>
+    ws!(call!(keyword, "in")) >>
+    body: call!(expression) >>
+    (bindings, body)));
+
+named!(pub let_binding<&[u8], (Name, Expression)>,
+  do_parse!(
+    name: ws!(call!(name)) >>
+    ws!(tag!("=")) >>
+    value: call!(expression) >>
+    (name, value)));
+
+named!(match_clauses<&[u8], Clauses>,
   map_opt!(
     separated_list!(ws!(tag!(",")), call!(match_clause)),
     |clauses| {
@@ -85,16 +255,104 @@ named!(match_clauses<&[u8], (Vec<(Matcher, Expression)>, Expression)>,
 named!(match_clause<&[u8], (Clause, Expression)>,
   do_parse!(
     clause: alt!(
-        map!(call!(expression), |e| Clause::Matcher(Matcher::Value(e))) |
+        map!(
+            pair!(call!(matcher), opt!(complete!(call!(guard)))),
+            |(matcher, guard)| Clause::Matcher(matcher, guard)
+        ) |
+        map!(
+            preceded!(ws!(tag!("_")), call!(guard)),
+            |guard| Clause::Matcher(Matcher::Wildcard, Some(guard))
+        ) |
         map!(ws!(tag!("_")), |_| Clause::Default_)
     ) >>
     ws!(tag!("=>")) >>
     value: call!(expression) >>
     (clause, value)));
 
+named!(guard<&[u8], Expression>,
+  preceded!(ws!(call!(keyword, "if")), call!(expression)));
+
+named!(pub matcher<&[u8], Matcher>,
+  alt!(
+    do_parse!(
+      name: ws!(call!(name)) >>
+      ws!(tag!("@")) >>
+      matcher: call!(alternatives) >>
+      (Matcher::Binding(name, box matcher))) |
+    call!(tuple_matcher) |
+    call!(alternatives) |
+    do_parse!(
+      ws!(call!(keyword, "let")) >>
+      name: ws!(call!(name)) >>
+      (Matcher::Binding(name, box Matcher::Wildcard)))));
+
+named!(tuple_matcher<&[u8], Matcher>,
+  map_opt!(
+    delimited!(
+      ws!(tag!("(")),
+      separated_nonempty_list!(
+        ws!(tag!(",")),
+        alt!(call!(matcher) | map!(ws!(tag!("_")), |_| Matcher::Wildcard))
+      ),
+      ws!(tag!(")"))
+    ),
+    |matchers: Vec<Matcher>| if matchers.len() > 1 {
+        Some(Matcher::Tuple(matchers))
+    } else {
+        None
+    }));
+
+named!(alternatives<&[u8], Matcher>,
+  map!(
+    separated_nonempty_list!(ws!(tag!("|")), call!(single_matcher)),
+    |mut matchers: Vec<Matcher>| if matchers.len() == 1 {
+        matchers.pop().unwrap()
+    } else {
+        Matcher::Alternatives(matchers)
+    }));
+
+named!(single_matcher<&[u8], Matcher>,
+  do_parse!(
+    start: call!(pattern_expression) >>
+    range: opt!(complete!(pair!(
+      ws!(alt_complete!(map!(tag!("..="), |_| true) | map!(tag!(".."), |_| false))),
+      call!(pattern_expression)
+    ))) >>
+    (match range {
+        Some((inclusive, end)) => Matcher::Range { start: start, end: end, inclusive: inclusive },
+        None => Matcher::Value(start),
+    })));
+
+fn checked_match(
+    with: Vec<Expression>,
+    matchers: Vec<(Matcher, Option<Expression>, Expression)>,
+    default: Expression,
+    span: Span,
+) -> Option<Match> {
+    let arity = with.len();
+    if matchers.iter().all(|&(ref matcher, _, _)| matches_arity(matcher, arity)) {
+        let mut match_ = Match::new(with, matchers, default);
+        match_.span = span;
+        Some(match_)
+    } else {
+        None
+    }
+}
+
+// Tuples only make sense matching as many values, and may not be nested.
+fn matches_arity(matcher: &Matcher, arity: usize) -> bool {
+    match matcher {
+        &Matcher::Tuple(ref matchers) => {
+            matchers.len() == arity && matchers.iter().all(|matcher| matches_arity(matcher, 1))
+        }
+        &Matcher::Wildcard => true,
+        _ => arity == 1,
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 enum Clause {
-    Matcher(Matcher),
+    Matcher(Matcher, Option<Expression>),
     Default_,
 }
 
@@ -112,11 +370,13 @@ fn default_clause_of(clauses: &Vec<(Clause, Expression)>) -> Option<Expression>
     default
 }
 
-fn matchers_of(clauses: Vec<(Clause, Expression)>) -> Vec<(Matcher, Expression)> {
+fn matchers_of(
+    clauses: Vec<(Clause, Expression)>,
+) -> Vec<(Matcher, Option<Expression>, Expression)> {
     let mut matchers = vec![];
     for (clause, expression) in clauses {
-        if let Clause::Matcher(matcher) = clause {
-            matchers.push((matcher, expression));
+        if let Clause::Matcher(matcher, guard) = clause {
+            matchers.push((matcher, guard, expression));
         }
     }
     matchers
@@ -227,8 +487,294 @@ mod tests {
         assert_eq!(operator(b"-"), as_done(b"", Operator::Subtract));
         assert_eq!(operator(b"*"), as_done(b"", Operator::Multiply));
         assert_eq!(operator(b"/"), as_done(b"", Operator::Divide));
-        assert_eq!(operator(b"^"), IResult::Error(nom::ErrorKind::OneOf));
+        assert_eq!(operator(b"%"), as_done(b"", Operator::Remainder));
+        assert_eq!(operator(b"mod x"), as_done(b" x", Operator::Modulo));
+        assert_eq!(operator(b"modx"), IResult::Error(nom::ErrorKind::Alt));
+        assert_eq!(operator(b"mod\nx"), as_done(b"\nx", Operator::Modulo));
+        assert_eq!(operator(b"mod(x)"), as_done(b"(x)", Operator::Modulo));
+        assert_eq!(operator(b"||"), as_done(b"", Operator::Or));
+        assert_eq!(operator(b"&&"), as_done(b"", Operator::And));
+        assert_eq!(operator(b"=="), as_done(b"", Operator::Equal));
+        assert_eq!(operator(b"!="), as_done(b"", Operator::NotEqual));
+        assert_eq!(operator(b"<"), as_done(b"", Operator::LessThan));
+        assert_eq!(operator(b"<="), as_done(b"", Operator::LessThanOrEqual));
+        assert_eq!(operator(b">"), as_done(b"", Operator::GreaterThan));
+        assert_eq!(operator(b">="), as_done(b"", Operator::GreaterThanOrEqual));
+        assert_eq!(operator(b"^"), as_done(b"", Operator::Power));
+        assert_eq!(operator(b"&"), as_done(b"", Operator::BitAnd));
+        assert_eq!(operator(b"| "), as_done(b" ", Operator::BitOr));
+        assert_eq!(operator(b"xor x"), as_done(b" x", Operator::BitXor));
+        assert_eq!(operator(b"xor\tx"), as_done(b"\tx", Operator::BitXor));
+        assert_eq!(operator(b"<<"), as_done(b"", Operator::ShiftLeft));
+        assert_eq!(operator(b">>"), as_done(b"", Operator::ShiftRight));
+        assert_eq!(operator(b"@"), IResult::Error(nom::ErrorKind::Alt));
+        assert_eq!(operator(b"=>"), IResult::Error(nom::ErrorKind::Alt));
         assert_eq!(operator(b"+ "), as_done(b" ", Operator::Add));
+        assert_eq!(operator(b"< 1"), as_done(b" 1", Operator::LessThan));
+    }
+
+    #[test]
+    fn comparison_precedence_test() {
+        assert_eq!(
+            expression(b"a + 1 < b * 2 == 1;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::Equal,
+                    box Expression::Operation(
+                        Operator::LessThan,
+                        box Expression::Operation(
+                            Operator::Add,
+                            box Expression::Operand(Operand::VarSubstitution(as_name("a"))),
+                            box Expression::Operand(Operand::I64(1))
+                        ),
+                        box Expression::Operation(
+                            Operator::Multiply,
+                            box Expression::Operand(Operand::VarSubstitution(as_name("b"))),
+                            box Expression::Operand(Operand::I64(2))
+                        )
+                    ),
+                    box Expression::Operand(Operand::I64(1))
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn comparison_chain_test() {
+        let var = |name| box Expression::Operand(Operand::VarSubstitution(as_name(name)));
+        assert_eq!(
+            expression(b"a == b != c;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::NotEqual,
+                    box Expression::Operation(Operator::Equal, var("a"), var("b")),
+                    var("c")
+                )
+            )
+        );
+        assert_eq!(
+            expression(b"a < b > c <= d;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::LessThanOrEqual,
+                    box Expression::Operation(
+                        Operator::GreaterThan,
+                        box Expression::Operation(Operator::LessThan, var("a"), var("b")),
+                        var("c")
+                    ),
+                    var("d")
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn remainder_chain_test() {
+        let var = |name| box Expression::Operand(Operand::VarSubstitution(as_name(name)));
+        assert_eq!(
+            expression(b"a % b * c;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::Multiply,
+                    box Expression::Operation(Operator::Remainder, var("a"), var("b")),
+                    var("c")
+                )
+            )
+        );
+        assert_eq!(
+            expression(b"a / b mod c % d;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::Remainder,
+                    box Expression::Operation(
+                        Operator::Modulo,
+                        box Expression::Operation(Operator::Divide, var("a"), var("b")),
+                        var("c")
+                    ),
+                    var("d")
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn bitwise_precedence_test() {
+        assert_eq!(
+            expression(b"a | b xor c & d == 1 << 2;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::BitOr,
+                    box Expression::Operand(Operand::VarSubstitution(as_name("a"))),
+                    box Expression::Operation(
+                        Operator::BitXor,
+                        box Expression::Operand(Operand::VarSubstitution(as_name("b"))),
+                        box Expression::Operation(
+                            Operator::BitAnd,
+                            box Expression::Operand(Operand::VarSubstitution(as_name("c"))),
+                            box Expression::Operation(
+                                Operator::Equal,
+                                box Expression::Operand(Operand::VarSubstitution(as_name("d"))),
+                                box Expression::Operation(
+                                    Operator::ShiftLeft,
+                                    box Expression::Operand(Operand::I64(1)),
+                                    box Expression::Operand(Operand::I64(2))
+                                )
+                            )
+                        )
+                    )
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn shift_chain_test() {
+        let int = |n| box Expression::Operand(Operand::I64(n));
+        assert_eq!(
+            expression(b"1 << 3 >> 2;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::ShiftRight,
+                    box Expression::Operation(Operator::ShiftLeft, int(1), int(3)),
+                    int(2)
+                )
+            )
+        );
+        assert_eq!(
+            expression(b"1 >> 2 << 3 + 1;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::ShiftLeft,
+                    box Expression::Operation(Operator::ShiftRight, int(1), int(2)),
+                    box Expression::Operation(Operator::Add, int(3), int(1))
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn logical_test() {
+        assert_eq!(
+            expression(b"!a || b && c != 0;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::Or,
+                    box Expression::Operand(Operand::Not(
+                        box Operand::VarSubstitution(as_name("a"))
+                    )),
+                    box Expression::Operation(
+                        Operator::And,
+                        box Expression::Operand(Operand::VarSubstitution(as_name("b"))),
+                        box Expression::Operation(
+                            Operator::NotEqual,
+                            box Expression::Operand(Operand::VarSubstitution(as_name("c"))),
+                            box Expression::Operand(Operand::I64(0))
+                        )
+                    )
+                )
+            )
+        );
+        assert_eq!(
+            operand(b"!!(x < 1)"),
+            as_done(
+                b"",
+                Operand::Not(box Operand::Not(box Operand::Group(
+                    box Expression::Operation(
+                        Operator::LessThan,
+                        box Expression::Operand(Operand::VarSubstitution(as_name("x"))),
+                        box Expression::Operand(Operand::I64(1))
+                    )
+                )))
+            )
+        );
+    }
+
+    #[test]
+    fn unary_test() {
+        assert_eq!(
+            operand(b"-x)"),
+            as_done(b")", Operand::Negate(box Operand::VarSubstitution(as_name("x"))))
+        );
+        assert_eq!(operand(b"+x)"), as_done(b")", Operand::VarSubstitution(as_name("x"))));
+        assert_eq!(operand(b"--1"), as_done(b"", Operand::Negate(box Operand::I64(-1))));
+        // `-` applies to a whole power, whose exponents associate to the right.
+        let power = |base: Operand| {
+            Operand::Negate(box Operand::Group(box Expression::Operation(
+                Operator::Power,
+                box Expression::Operand(base),
+                box Expression::Operation(
+                    Operator::Power,
+                    box Expression::Operand(Operand::I64(2)),
+                    box Expression::Operand(Operand::VarSubstitution(as_name("y")))
+                )
+            )))
+        };
+        assert_eq!(
+            operand(b"-x ^ 2 ^ y)"),
+            as_done(b")", power(Operand::VarSubstitution(as_name("x"))))
+        );
+        assert_eq!(operand(b"-3 ^ 2 ^ y)"), as_done(b")", power(Operand::I64(3))));
+        assert_eq!(
+            expression(b"-x ^ 2 * 3;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::Multiply,
+                    box Expression::Operand(Operand::Negate(box Operand::Group(
+                        box Expression::Operation(
+                            Operator::Power,
+                            box Expression::Operand(Operand::VarSubstitution(as_name("x"))),
+                            box Expression::Operand(Operand::I64(2))
+                        )
+                    ))),
+                    box Expression::Operand(Operand::I64(3))
+                )
+            )
+        );
+        assert_eq!(
+            expression(b"a - -f(n) * -(b + 1);"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::Subtract,
+                    box Expression::Operand(Operand::VarSubstitution(as_name("a"))),
+                    box Expression::Operation(
+                        Operator::Multiply,
+                        box Expression::Operand(Operand::Negate(box Operand::FnApplication(
+                            as_name("f"),
+                            vec![Expression::Operand(Operand::VarSubstitution(as_name("n")))]
+                        ))),
+                        box Expression::Operand(Operand::Negate(box Operand::Group(
+                            box Expression::Operation(
+                                Operator::Add,
+                                box Expression::Operand(Operand::VarSubstitution(as_name("b"))),
+                                box Expression::Operand(Operand::I64(1))
+                            )
+                        )))
+                    )
+                )
+            )
+        );
+        assert_eq!(
+            expression(b"1 -2;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::Subtract,
+                    box Expression::Operand(Operand::I64(1)),
+                    box Expression::Operand(Operand::I64(2))
+                )
+            )
+        );
     }
 
     #[test]
@@ -293,22 +839,25 @@ mod tests {
             as_done(
                 b"",
                 Operand::Match(Match {
-                    with: box Expression::Operation(
+                    with: vec![Expression::Operation(
                         Operator::Multiply,
                         box Expression::Operand(Operand::I64(5)),
                         box Expression::Operand(Operand::VarSubstitution(as_name("x")))
-                    ),
+                    )],
                     clauses: vec![
                         (
                             Matcher::Value(Expression::Operand(Operand::I64(1))),
+                            None,
                             Expression::Operand(Operand::I64(2)),
                         ),
                         (
                             Matcher::Value(Expression::Operand(Operand::I64(3))),
+                            None,
                             Expression::Operand(Operand::I64(5)),
                         ),
                     ],
                     default: box Expression::Operand(Operand::I64(11)),
+                    span: Span::default(),
                 })
             )
         );
@@ -320,7 +869,33 @@ mod tests {
         assert_eq!(i64(b"794"), as_done(b"", 794));
         assert_eq!(i64(b"-1"), as_done(b"", -1));
         assert_eq!(i64(b"-390"), as_done(b"", -390));
-        assert_eq!(i64(b"a"), IResult::Error(nom::ErrorKind::TakeWhile1));
+        assert_eq!(i64(b"1-2-3"), as_done(b"-2-3", 1));
+        assert_eq!(i64(b"1_000_000"), as_done(b"", 1000000));
+        assert_eq!(i64(b"0xFf_00"), as_done(b"", 0xff00));
+        assert_eq!(i64(b"-0b1010"), as_done(b"", -10));
+        assert_eq!(i64(b"0b12"), as_done(b"2", 1));
+        assert_eq!(i64(b"0x_1"), as_done(b"x_1", 0));
+        assert_eq!(i64(b"-9223372036854775808"), as_done(b"", i64::min_value()));
+        assert_eq!(i64(b"0x7fffffffffffffff"), as_done(b"", i64::max_value()));
+        assert!(i64(b"a").is_err());
+        assert!(i64(b"-a").is_err());
+        assert!(i64(b"_1").is_err());
+    }
+
+    #[test]
+    fn out_of_range_literal_test() {
+        take_out_of_range_literal();
+        assert!(i64(b"9223372036854775808;").is_err());
+        assert_eq!(
+            take_out_of_range_literal(),
+            Some(("9223372036854775808".to_string(), Span::default()))
+        );
+        assert!(i64(b"-0x8000_0000_0000_0001").is_err());
+        assert_eq!(
+            take_out_of_range_literal(),
+            Some(("-0x8000_0000_0000_0001".to_string(), Span::default()))
+        );
+        assert_eq!(take_out_of_range_literal(), None);
     }
 
     #[test]
@@ -448,9 +1023,10 @@ mod tests {
             as_done(
                 b"",
                 Match {
-                    with: box Expression::Operand(Operand::VarSubstitution(as_name("x"))),
+                    with: vec![Expression::Operand(Operand::VarSubstitution(as_name("x")))],
                     clauses: vec![],
                     default: box Expression::Operand(Operand::I64(-1)),
+                    span: Span::default(),
                 }
             )
         );
@@ -460,13 +1036,14 @@ mod tests {
             as_done(
                 b"",
                 Match {
-                    with: box Expression::Operation(
+                    with: vec![Expression::Operation(
                         Operator::Add,
                         box Expression::Operand(Operand::VarSubstitution(as_name("x"))),
                         box Expression::Operand(Operand::I64(5))
-                    ),
+                    )],
                     clauses: vec![],
                     default: box Expression::Operand(Operand::VarSubstitution(as_name("y"))),
+                    span: Span::default(),
                 }
             )
         );
@@ -479,18 +1056,20 @@ mod tests {
             as_done(
                 b"",
                 Match {
-                    with: box Expression::Operation(
+                    with: vec![Expression::Operation(
                         Operator::Add,
                         box Expression::Operand(Operand::VarSubstitution(as_name("x"))),
                         box Expression::Operand(Operand::I64(5))
-                    ),
+                    )],
                     clauses: vec![
                         (
                             Matcher::Value(Expression::Operand(Operand::I64(1))),
+                            None,
                             Expression::Operand(Operand::I64(2)),
                         ),
                     ],
                     default: box Expression::Operand(Operand::VarSubstitution(as_name("zz"))),
+                    span: Span::default(),
                 }
             )
         );
@@ -499,22 +1078,25 @@ mod tests {
             as_done(
                 b"",
                 Match {
-                    with: box Expression::Operation(
+                    with: vec![Expression::Operation(
                         Operator::Add,
                         box Expression::Operand(Operand::VarSubstitution(as_name("x"))),
                         box Expression::Operand(Operand::I64(5))
-                    ),
+                    )],
                     clauses: vec![
                         (
                             Matcher::Value(Expression::Operand(Operand::I64(32))),
+                            None,
                             Expression::Operand(Operand::I64(64)),
                         ),
                         (
                             Matcher::Value(Expression::Operand(Operand::I64(33))),
+                            None,
                             Expression::Operand(Operand::I64(128)),
                         ),
                     ],
                     default: box Expression::Operand(Operand::I64(-9)),
+                    span: Span::default(),
                 }
             )
         );
@@ -523,29 +1105,396 @@ mod tests {
             as_done(
                 b"",
                 Match {
-                    with: box Expression::Operation(
+                    with: vec![Expression::Operation(
                         Operator::Multiply,
                         box Expression::Operand(Operand::I64(5)),
                         box Expression::Operand(Operand::VarSubstitution(as_name("x")))
-                    ),
+                    )],
                     clauses: vec![
                         (
                             Matcher::Value(Expression::Operand(Operand::I64(1))),
+                            None,
                             Expression::Operand(Operand::I64(2)),
                         ),
                         (
                             Matcher::Value(Expression::Operand(Operand::I64(3))),
+                            None,
                             Expression::Operand(Operand::I64(5)),
                         ),
                     ],
                     default: box Expression::Operand(Operand::VarSubstitution(as_name("y"))),
+                    span: Span::default(),
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn guard_test() {
+        assert_eq!(
+            match_(b"match n { 0 if a => 1, _ if n > 10 => 2, _ => 3 }"),
+            as_done(
+                b"",
+                Match {
+                    with: vec![Expression::Operand(Operand::VarSubstitution(as_name("n")))],
+                    clauses: vec![
+                        (
+                            Matcher::Value(Expression::Operand(Operand::I64(0))),
+                            Some(Expression::Operand(Operand::VarSubstitution(as_name("a")))),
+                            Expression::Operand(Operand::I64(1)),
+                        ),
+                        (
+                            Matcher::Wildcard,
+                            Some(Expression::Operation(
+                                Operator::GreaterThan,
+                                box Expression::Operand(Operand::VarSubstitution(as_name("n"))),
+                                box Expression::Operand(Operand::I64(10))
+                            )),
+                            Expression::Operand(Operand::I64(2)),
+                        ),
+                    ],
+                    default: box Expression::Operand(Operand::I64(3)),
+                    span: Span::default(),
+                }
+            )
+        );
+        assert_eq!(
+            match_clause(b"1 | 2 if x => 3"),
+            as_done(
+                b"",
+                (
+                    Clause::Matcher(
+                        Matcher::Alternatives(vec![
+                            Matcher::Value(Expression::Operand(Operand::I64(1))),
+                            Matcher::Value(Expression::Operand(Operand::I64(2))),
+                        ]),
+                        Some(Expression::Operand(Operand::VarSubstitution(as_name("x"))))
+                    ),
+                    Expression::Operand(Operand::I64(3))
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn binding_test() {
+        assert_eq!(
+            matcher(b"n @ 1..10 | 20 =>"),
+            as_done(
+                b"=>",
+                Matcher::Binding(
+                    as_name("n"),
+                    box Matcher::Alternatives(vec![
+                        Matcher::Range {
+                            start: Expression::Operand(Operand::I64(1)),
+                            end: Expression::Operand(Operand::I64(10)),
+                            inclusive: false,
+                        },
+                        Matcher::Value(Expression::Operand(Operand::I64(20))),
+                    ])
+                )
+            )
+        );
+        assert_eq!(
+            matcher(b"let n if"),
+            as_done(b"if", Matcher::Binding(as_name("n"), box Matcher::Wildcard))
+        );
+        assert_eq!(
+            matcher(b"n =>"),
+            as_done(
+                b"=>",
+                Matcher::Value(Expression::Operand(Operand::VarSubstitution(as_name("n"))))
+            )
+        );
+        assert_eq!(
+            matcher(b"let n = 1 in n =>"),
+            as_done(
+                b"=>",
+                Matcher::Value(Expression::Operand(Operand::Let(
+                    vec![(as_name("n"), Expression::Operand(Operand::I64(1)))],
+                    box Expression::Operand(Operand::VarSubstitution(as_name("n")))
+                )))
+            )
+        );
+    }
+
+    #[test]
+    fn tuple_match_test() {
+        let var = |n| Expression::Operand(Operand::VarSubstitution(as_name(n)));
+        assert_eq!(
+            match_(b"match (m, n) { (0, let k) => k, (_, 0 | 1) => 1, _ => 2 }"),
+            as_done(
+                b"",
+                Match::new(
+                    vec![var("m"), var("n")],
+                    vec![
+                        (
+                            Matcher::Tuple(vec![
+                                Matcher::Value(Expression::Operand(Operand::I64(0))),
+                                Matcher::Binding(as_name("k"), box Matcher::Wildcard),
+                            ]),
+                            None,
+                            var("k"),
+                        ),
+                        (
+                            Matcher::Tuple(vec![
+                                Matcher::Wildcard,
+                                Matcher::Alternatives(vec![
+                                    Matcher::Value(Expression::Operand(Operand::I64(0))),
+                                    Matcher::Value(Expression::Operand(Operand::I64(1))),
+                                ]),
+                            ]),
+                            None,
+                            Expression::Operand(Operand::I64(1)),
+                        ),
+                    ],
+                    Expression::Operand(Operand::I64(2)),
+                )
+            )
+        );
+        // A parenthesised scrutinee is still a single value.
+        assert_eq!(
+            match_(b"match (m) { 0 => 1, _ => 2 }"),
+            as_done(
+                b"",
+                Match::new(
+                    vec![Expression::Operand(Operand::Group(box var("m")))],
+                    vec![(
+                        Matcher::Value(Expression::Operand(Operand::I64(0))),
+                        None,
+                        Expression::Operand(Operand::I64(1)),
+                    )],
+                    Expression::Operand(Operand::I64(2)),
+                )
+            )
+        );
+        // Tuples must have as many elements as there are values, and may not be nested.
+        assert!(match_(b"match (m, n) { (0, 1, 2) => 1, _ => 2 }").is_err());
+        assert!(match_(b"match (m, n) { 0 => 1, _ => 2 }").is_err());
+        assert!(match_(b"match m { (0, 1) => 1, _ => 2 }").is_err());
+        assert!(match_(b"match (m, n) { ((0, 1), 2) => 1, _ => 2 }").is_err());
+    }
+
+    #[test]
+    fn matcher_test() {
+        assert_eq!(
+            matcher(b"1..2 =>"),
+            as_done(
+                b"=>",
+                Matcher::Range {
+                    start: Expression::Operand(Operand::I64(1)),
+                    end: Expression::Operand(Operand::I64(2)),
+                    inclusive: false,
                 }
             )
         );
+        assert_eq!(
+            matcher(b"-5..=x + 1 =>"),
+            as_done(
+                b"=>",
+                Matcher::Range {
+                    start: Expression::Operand(Operand::I64(-5)),
+                    end: Expression::Operation(
+                        Operator::Add,
+                        box Expression::Operand(Operand::VarSubstitution(as_name("x"))),
+                        box Expression::Operand(Operand::I64(1))
+                    ),
+                    inclusive: true,
+                }
+            )
+        );
+        assert_eq!(
+            matcher(b"5..6 | 9 =>"),
+            as_done(
+                b"=>",
+                Matcher::Alternatives(vec![
+                    Matcher::Range {
+                        start: Expression::Operand(Operand::I64(5)),
+                        end: Expression::Operand(Operand::I64(6)),
+                        inclusive: false,
+                    },
+                    Matcher::Value(Expression::Operand(Operand::I64(9))),
+                ])
+            )
+        );
+        assert_eq!(
+            matcher(b"(a | 1) & 3 | 9 =>"),
+            as_done(
+                b"=>",
+                Matcher::Alternatives(vec![
+                    Matcher::Value(Expression::Operation(
+                        Operator::BitAnd,
+                        box Expression::Operand(Operand::Group(box Expression::Operation(
+                            Operator::BitOr,
+                            box Expression::Operand(Operand::VarSubstitution(as_name("a"))),
+                            box Expression::Operand(Operand::I64(1))
+                        ))),
+                        box Expression::Operand(Operand::I64(3))
+                    )),
+                    Matcher::Value(Expression::Operand(Operand::I64(9))),
+                ])
+            )
+        );
+        assert_eq!(
+            match_(b"match x { 0 => 0, 1..2 => 1, 5..6 | 9 => 3, _ => 4 }"),
+            as_done(
+                b"",
+                Match {
+                    with: vec![Expression::Operand(Operand::VarSubstitution(as_name("x")))],
+                    clauses: vec![
+                        (
+                            Matcher::Value(Expression::Operand(Operand::I64(0))),
+                            None,
+                            Expression::Operand(Operand::I64(0)),
+                        ),
+                        (
+                            Matcher::Range {
+                                start: Expression::Operand(Operand::I64(1)),
+                                end: Expression::Operand(Operand::I64(2)),
+                                inclusive: false,
+                            },
+                            None,
+                            Expression::Operand(Operand::I64(1)),
+                        ),
+                        (
+                            Matcher::Alternatives(vec![
+                                Matcher::Range {
+                                    start: Expression::Operand(Operand::I64(5)),
+                                    end: Expression::Operand(Operand::I64(6)),
+                                    inclusive: false,
+                                },
+                                Matcher::Value(Expression::Operand(Operand::I64(9))),
+                            ]),
+                            None,
+                            Expression::Operand(Operand::I64(3)),
+                        ),
+                    ],
+                    default: box Expression::Operand(Operand::I64(4)),
+                    span: Span::default(),
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn let_test() {
+        assert_eq!(
+            operand(b"let a = 1, b = a + 1 in a * b;"),
+            as_done(
+                b";",
+                Operand::Let(
+                    vec![
+                        (as_name("a"), Expression::Operand(Operand::I64(1))),
+                        (
+                            as_name("b"),
+                            Expression::Operation(
+                                Operator::Add,
+                                box Expression::Operand(Operand::VarSubstitution(as_name("a"))),
+                                box Expression::Operand(Operand::I64(1))
+                            ),
+                        ),
+                    ],
+                    box Expression::Operation(
+                        Operator::Multiply,
+                        box Expression::Operand(Operand::VarSubstitution(as_name("a"))),
+                        box Expression::Operand(Operand::VarSubstitution(as_name("b")))
+                    )
+                )
+            )
+        );
+        assert_eq!(
+            expressions(b"let a = 1 in a, 2)"),
+            as_done(
+                b")",
+                vec![
+                    Expression::Operand(Operand::Let(
+                        vec![(as_name("a"), Expression::Operand(Operand::I64(1)))],
+                        box Expression::Operand(Operand::VarSubstitution(as_name("a")))
+                    )),
+                    Expression::Operand(Operand::I64(2)),
+                ]
+            )
+        );
+        assert!(let_(b"let in 1;").is_err());
+        assert_eq!(
+            let_(b"let\ta = 1 in\n a;"),
+            as_done(
+                b";",
+                (
+                    vec![(as_name("a"), Expression::Operand(Operand::I64(1)))],
+                    Expression::Operand(Operand::VarSubstitution(as_name("a"))),
+                )
+            )
+        );
+        assert!(let_(b"leta = 1 in a;").is_err());
+    }
+
+    #[test]
+    fn if_test() {
+        assert_eq!(
+            if_(b"if x then 1 else 2"),
+            as_done(
+                b"",
+                (
+                    Expression::Operand(Operand::VarSubstitution(as_name("x"))),
+                    Expression::Operand(Operand::I64(1)),
+                    Expression::Operand(Operand::I64(2)),
+                )
+            )
+        );
+        assert_eq!(
+            expression(b"1 + if x - 1 then if y then 2 else 3 else 4 * 5;"),
+            as_done(
+                b";",
+                Expression::Operation(
+                    Operator::Add,
+                    box Expression::Operand(Operand::I64(1)),
+                    box Expression::Operand(Operand::If(
+                        box Expression::Operation(
+                            Operator::Subtract,
+                            box Expression::Operand(Operand::VarSubstitution(as_name("x"))),
+                            box Expression::Operand(Operand::I64(1))
+                        ),
+                        box Expression::Operand(Operand::If(
+                            box Expression::Operand(Operand::VarSubstitution(as_name("y"))),
+                            box Expression::Operand(Operand::I64(2)),
+                            box Expression::Operand(Operand::I64(3))
+                        )),
+                        box Expression::Operation(
+                            Operator::Multiply,
+                            box Expression::Operand(Operand::I64(4)),
+                            box Expression::Operand(Operand::I64(5))
+                        )
+                    ))
+                )
+            )
+        );
+        assert_eq!(if_(b"if x then 1;"), IResult::Error(ErrorKind::Tag));
+
+        // Keywords need only be kept apart from names.
+        let if_x = (
+            Expression::Operand(Operand::VarSubstitution(as_name("x"))),
+            Expression::Operand(Operand::I64(1)),
+            Expression::Operand(Operand::I64(0)),
+        );
+        assert_eq!(if_(b"if x then\n  1 else\n0;"), as_done(b";", if_x.clone()));
+        assert_eq!(
+            if_(b"if(x)then(1)else 0;"),
+            as_done(
+                b";",
+                (
+                    Expression::Operand(Operand::Group(box if_x.0.clone())),
+                    Expression::Operand(Operand::Group(box if_x.1.clone())),
+                    if_x.2.clone(),
+                )
+            )
+        );
+        assert!(if_(b"ifx then 1 else 0;").is_err());
+        assert!(if_(b"if x thenx else 0;").is_err());
     }
 
     fn as_name(s: &str) -> Name {
-        Name(s.to_string())
+        Name::new(s)
     }
 
     fn as_done<O, E>(remaining: &[u8], output: O) -> IResult<&[u8], O, E> {
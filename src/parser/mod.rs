@@ -1,11 +1,23 @@
+// Replaces nom's `ws!`, so that comments can go anywhere whitespace can.
+macro_rules! ws (
+  ($i:expr, $($args:tt)*) => ({
+      use $crate::parser::blank;
+      sep!($i, blank, $($args)*)
+  })
+);
+
 #[cfg_attr(rustfmt, rustfmt_skip)]
 
+mod comment;
+mod span;
 mod name;
 mod expression;
 mod statement;
 mod shunting_yard;
 mod program;
 
+pub use self::comment::*;
+pub use self::span::*;
 pub use self::name::*;
 pub use self::expression::*;
 pub use self::statement::*;
@@ -13,41 +25,238 @@ pub use self::program::*;
 
 use super::*;
 use nom::{simple_errors, IResult, Needed};
+use std::str;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Error {
-    RemainingInput(Vec<u8>, Program),
+    /// The span is of the input that couldn't be parsed.
+    RemainingInput(Vec<u8>, Program, Span),
+    /// Input that doesn't parse as what should be there, such as a statement.
+    Syntax {
+        expected: &'static str,
+        span: Span,
+    },
     Nom(simple_errors::Err<u32>),
     NomIncomplete(Needed),
+    /// The span is of the first byte that isn't part of valid UTF-8.
+    InvalidUtf8(Span),
     DuplicateInput(Name),
+    /// An integer literal, as written, that doesn't fit in an `i64`.
+    IntegerOutOfRange(String, Span),
+    NonContiguousClauses(Name),
+    /// A clause directly after the function's clause that matches any arguments.
+    ClauseAfterCatchAll(Name),
+    ClauseArityMismatch {
+        name: Name,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl Error {
+    /// Where in the source the error is, if nom hasn't lost track.
+    pub fn span(&self) -> Option<Span> {
+        match *self {
+            Error::RemainingInput(_, _, span)
+            | Error::Syntax { span, .. }
+            | Error::InvalidUtf8(span)
+            | Error::IntegerOutOfRange(_, span) => located(span),
+            Error::DuplicateInput(ref name)
+            | Error::NonContiguousClauses(ref name)
+            | Error::ClauseAfterCatchAll(ref name)
+            | Error::ClauseArityMismatch { ref name, .. } => located(name.1),
+            Error::Nom(_) | Error::NomIncomplete(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::RemainingInput(..) => write!(f, "expected the input to end here"),
+            Error::Syntax { expected, .. } => write!(f, "expected {}", expected),
+            Error::Nom(_) => write!(f, "syntax error"),
+            Error::NomIncomplete(_) => write!(f, "unexpected end of input"),
+            Error::InvalidUtf8(_) => write!(f, "the source isn't valid UTF-8"),
+            Error::DuplicateInput(ref name) => {
+                write!(f, "input `{}` is declared more than once", name)
+            }
+            Error::IntegerOutOfRange(ref literal, _) => {
+                write!(f, "integer literal `{}` doesn't fit in 64 bits", literal)
+            }
+            Error::NonContiguousClauses(ref name) => write!(
+                f,
+                "clauses of `{}` must be written together, but other statements come between",
+                name
+            ),
+            Error::ClauseAfterCatchAll(ref name) => write!(
+                f,
+                "clause of `{}` can never be used, as an earlier clause matches any arguments",
+                name
+            ),
+            Error::ClauseArityMismatch {
+                ref name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "clause of `{}` takes {}, but earlier clauses take {}",
+                name,
+                count(found, "parameter"),
+                expected
+            ),
+        }
+    }
 }
 
+/// Parses a program, giving the first error in it if there are any.
 pub fn parse(s: &[u8]) -> Result<Program, Error> {
-    let program = match program(s) {
-        IResult::Done(&[], program) => Ok(program),
-        IResult::Done(i, o) => Err(Error::RemainingInput(i.to_vec(), o)),
-        IResult::Error(e) => Err(Error::Nom(e)),
-        IResult::Incomplete(n) => Err(Error::NomIncomplete(n)),
-    }?;
+    let (program, errors) = parse_recovering(s);
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(program),
+    }
+}
+
+/// Parses a program that has no errors into its inputs, its statements as written with their
+/// doc comments, and its outputs.
+pub fn parse_items(
+    s: &[u8],
+) -> Result<(Vec<Name>, Vec<(Option<String>, Item)>, Vec<Name>), Error> {
+    let (program, items, errors) = parse_recovering_items(s);
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok((program.inputs, items, program.outputs)),
+    }
+}
+
+/// Parses as much of a program as it can, carrying on after a malformed statement from the
+/// next `;`. Gives every error found, in the order they appear in the source, along with the
+/// program as far as it could be parsed.
+pub fn parse_recovering(s: &[u8]) -> (Program, Vec<Error>) {
+    let (program, _, errors) = parse_recovering_items(s);
+    (program, errors)
+}
+
+// Like `parse_recovering`, but also gives the statements as written, before the clauses of
+// piecewise functions are grouped.
+fn parse_recovering_items(s: &[u8]) -> (Program, Vec<(Option<String>, Item)>, Vec<Error>) {
+    if let Err(e) = check_utf8(s) {
+        return (
+            Program::new(vec![], Statements::new(vec![]), vec![]),
+            vec![],
+            vec![e],
+        );
+    }
+    let mut errors = vec![];
+    let (program, items) = with_source(s, || {
+        let expected_inputs = "`inputs`, then names separated by commas and `;`";
+        let (mut rest, inputs) = recover(s, inputs, expected_inputs, &mut errors);
+        let mut items = vec![];
+        let outputs = loop {
+            if let IResult::Done(after, outputs) = outputs(rest) {
+                rest = after;
+                break outputs;
+            }
+            let (after, _) = blank(rest).unwrap();
+            if after.is_empty() {
+                errors.push(Error::Syntax {
+                    expected: "`outputs`, then names separated by commas and `;`",
+                    span: span(after, after),
+                });
+                rest = after;
+                break vec![];
+            }
+            let (after, item) = recover(rest, documented_item, "a statement", &mut errors);
+            rest = after;
+            items.extend(item);
+        };
+        let statements = group_clauses_recovering(items.clone(), &mut errors);
+        let program = Program::new(inputs.unwrap_or_default(), statements, outputs);
+        if !rest.is_empty() {
+            errors.push(Error::RemainingInput(
+                rest.to_vec(),
+                program.clone(),
+                span(rest, &rest[rest.len()..]),
+            ));
+        }
+        (program, items)
+    });
     let mut input_map = HashSet::new();
     for input in &program.inputs {
         if input_map.contains(input) {
-            return Err(Error::DuplicateInput(input.clone()));
+            errors.push(Error::DuplicateInput(input.clone()));
         }
         input_map.insert(input.clone());
     }
-    Ok(program)
+    errors.sort_by_key(|error| error.span().map(|span| span.start));
+    (program, items, errors)
+}
+
+// Runs the parser, or if it fails, notes why and skips past the next `;`.
+fn recover<'a, O, F>(
+    input: &'a [u8],
+    parser: F,
+    expected: &'static str,
+    errors: &mut Vec<Error>,
+) -> (&'a [u8], Option<O>)
+where
+    F: Fn(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+    take_out_of_range_literal();
+    if let IResult::Done(rest, output) = parser(input) {
+        return (rest, Some(output));
+    }
+    let rest = skip_statement(input);
+    errors.push(match take_out_of_range_literal() {
+        Some((literal, span)) => Error::IntegerOutOfRange(literal, span),
+        None => Error::Syntax {
+            expected: expected,
+            span: span(input, rest),
+        },
+    });
+    (rest, None)
+}
+
+// The input after the next `;` that isn't in a comment, or none if there isn't one.
+fn skip_statement(mut input: &[u8]) -> &[u8] {
+    loop {
+        input = blank(input).unwrap().0;
+        match input.first() {
+            Some(&b';') => return &input[1..],
+            Some(_) => input = &input[1..],
+            None => return input,
+        }
+    }
 }
 
 pub fn parse_one(s: &[u8]) -> Result<Statement, Error> {
-    match statement(s) {
+    check_utf8(s)?;
+    take_out_of_range_literal();
+    let statement = with_source(s, || match statement(s) {
         IResult::Done(&[], statement) => Ok(statement),
         IResult::Done(i, o) => Err(Error::RemainingInput(
             i.to_vec(),
-            Program::new(vec![], Statements(vec![o]), vec![]),
+            Program::new(vec![], Statements::new(vec![o]), vec![]),
+            span(i, &i[i.len()..]),
         )),
         IResult::Error(e) => Err(Error::Nom(e)),
         IResult::Incomplete(n) => Err(Error::NomIncomplete(n)),
+    });
+    statement.map_err(literal_error)
+}
+
+fn check_utf8(s: &[u8]) -> Result<(), Error> {
+    str::from_utf8(s)
+        .map(|_| ())
+        .map_err(|e| Error::InvalidUtf8(Span::new(s, e.valid_up_to(), e.valid_up_to() + 1)))
+}
+
+// Nom gives up at a literal it can't parse, so an out-of-range literal explains the failure.
+fn literal_error(error: Error) -> Error {
+    match take_out_of_range_literal() {
+        Some((literal, span)) => Error::IntegerOutOfRange(literal, span),
+        None => error,
     }
 }
 
@@ -84,6 +293,175 @@ outputs x, i;
         ).unwrap_err();
     }
 
+    #[test]
+    fn comments() {
+        let program = parse(
+            b"# Doubles n.
+inputs /* the input */ n; // Not a doc comment.
+/// Doubles.
+///
+/// Twice.
+f(x) = x * /* two */ 2;
+## Uses f.
+m = f(n # the input
+);
+/* done */ outputs m; # end",
+        ).unwrap();
+        assert_eq!(
+            program.statements.1,
+            vec![
+                Some("Doubles.\n\nTwice.".to_string()),
+                Some("Uses f.".to_string()),
+            ]
+        );
+        assert_eq!(
+            format!("{}", program),
+            "inputs n;\n/// Doubles.\n///\n/// Twice.\nf(x) = x * 2;\n/// Uses f.\nm = f(n);\n\
+             outputs m;"
+        );
+
+        // The doc comments of piecewise definitions are joined.
+        let program = parse(b"inputs; /// One.\nf(0) = 1; /// Two.\nf(n) = n; outputs;").unwrap();
+        assert_eq!(program.statements.1, vec![Some("One.\nTwo.".to_string())]);
+
+        parse(b"inputs; x = 1; /* unterminated outputs x;").unwrap_err();
+    }
+
+    #[test]
+    fn comments_after_keywords() {
+        let program = parse(
+            b"inputs# c
+  a;
+f(x) = y where/* c */
+  y = x;
+z = if/* c */a then# c
+  1 else// c
+  2;
+w = let# c
+  b = a in/* c */
+  b mod# c
+  2 xor/* c */
+  1;
+m = match/* c */
+  a { _ => 0 };
+outputs/* c */
+  z, w, m;",
+        ).unwrap();
+        assert_eq!(
+            format!("{}", program),
+            "inputs a;\nf(x) = y where y = x;\nz = if a then 1 else 2;\n\
+             w = let b = a in b mod 2 xor 1;\nm = match a { _ => 0, };\noutputs z, w, m;"
+        );
+    }
+
+    #[test]
+    fn out_of_range_literals() {
+        let error = parse(b"inputs; x = 1 + 99999999999999999999; outputs x;").unwrap_err();
+        assert_eq!(
+            error,
+            Error::IntegerOutOfRange("99999999999999999999".to_string(), Span::default())
+        );
+        assert_eq!(error.span().map(|span| (span.start, span.end)), Some((16, 36)));
+        let binary = format!("0b1_{}", "0".repeat(63));
+        assert_eq!(
+            parse_one(format!("f(x) = match x {{ {} => 1, _ => 0 }};", binary).as_bytes()),
+            Err(Error::IntegerOutOfRange(binary, Span::default()))
+        );
+        parse(b"inputs; x = 0x7FFF_FFFF_FFFF_FFFF; outputs x;").unwrap();
+    }
+
+    #[test]
+    fn unicode_names() {
+        let program = parse("inputs \u{3b1}; \u{394}x = \u{3b1} * 2; outputs \u{394}x;".as_bytes());
+        assert_eq!(
+            format!("{}", program.unwrap()),
+            "inputs \u{3b1};\n\u{394}x = \u{3b1} * 2;\noutputs \u{394}x;"
+        );
+        let error = parse(b"inputs; x = 1; # \xff\noutputs x;").unwrap_err();
+        assert_eq!(error.span().map(|span| span.start), Some(17));
+    }
+
+    #[test]
+    fn spans() {
+        let source = b"inputs n;\nf(x) = x * 2;\nm = f(n) +\n  match n { 1 => 2, _ => n };\n\
+                       outputs m;";
+        let program = parse(source).unwrap();
+        let text = |span: Span| str::from_utf8(&source[span.start..span.end]).unwrap();
+        let (f, m) = (&program.statements.0[0], &program.statements.0[1]);
+        assert_eq!(text(f.span()), "f(x) = x * 2");
+        assert_eq!((f.span().line, f.span().column), (2, 1));
+        assert_eq!(text(m.span()), "m = f(n) +\n  match n { 1 => 2, _ => n }");
+        let (name, call, match_) = match *m {
+            Statement::VarAssignment(
+                ref name,
+                Expression::Operation(_, ref call, ref match_),
+                _,
+            ) => (name, call, match_),
+            _ => panic!("expected an operation"),
+        };
+        assert_eq!(text(name.1), "m");
+        assert_eq!(text(call.span()), "f(n)");
+        assert_eq!((match_.span().line, match_.span().column), (4, 3));
+        match *match_.unspanned() {
+            Expression::Operand(Operand::Match(ref match_)) => {
+                assert_eq!(text(match_.span()), "match n { 1 => 2, _ => n }")
+            }
+            _ => panic!("expected a match"),
+        }
+        assert_eq!(text(program.outputs[0].1), "m");
+
+        // Piecewise definitions span all their clauses.
+        let program = parse(b"inputs; f(0) = 1;\nf(n) = n; outputs;").unwrap();
+        assert_eq!(program.statements.0[0].span().end, 26);
+
+        let error = parse(b"inputs; x = 1; outputs x; y").unwrap_err();
+        assert_eq!(error.span().map(|span| (span.start, span.column)), Some((26, 27)));
+    }
+
+    #[test]
+    fn recovers_from_malformed_statements() {
+        let source = b"inputs a;\nx = a +;\ny = a * 2;\nf(n) = 99999999999999999999;\n\
+                       z = ) # ;\n;\noutputs y;";
+        let (program, errors) = parse_recovering(source);
+        assert_eq!(format!("{}", program), "inputs a;\ny = a * 2;\noutputs y;");
+        let errors: Vec<_> = errors
+            .iter()
+            .map(|error| {
+                let span = error.span().unwrap();
+                let text = str::from_utf8(&source[span.start..span.end]).unwrap();
+                (format!("{}", error), text, span.line)
+            })
+            .collect();
+        assert_eq!(
+            errors,
+            vec![
+                ("expected a statement".to_string(), "x = a +;", 2),
+                (
+                    "integer literal `99999999999999999999` doesn't fit in 64 bits".to_string(),
+                    "99999999999999999999",
+                    4,
+                ),
+                ("expected a statement".to_string(), "z = ) # ;\n;", 5),
+            ]
+        );
+
+        let (program, errors) = parse_recovering(b"inputs; x = 1; f(0) = 1; y = 2; f(n) = n;");
+        assert_eq!(
+            format!("{}", program),
+            "inputs;\nx = 1;\nf(_0) = match _0 { 0 => 1, _ => 0, };\ny = 2;\nf(n) = n;\noutputs;"
+        );
+        assert_eq!(
+            errors,
+            vec![
+                Error::NonContiguousClauses(Name::new("f")),
+                Error::Syntax {
+                    expected: "`outputs`, then names separated by commas and `;`",
+                    span: Span::default(),
+                },
+            ]
+        );
+    }
+
     fn parses_correctly_prop(input: Program) -> bool {
         format!("{}", parse(format!("{}", input).as_bytes()).unwrap()) == format!("{}", input)
     }
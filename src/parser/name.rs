@@ -1,18 +1,54 @@
 use super::super::*;
+use super::span;
 use std::str;
-use nom::is_space;
+use nom::{ErrorKind, IResult, Needed};
+use unicode_xid::UnicodeXID;
 
-named!(pub name<&[u8], Name>,
-  map!(
-    verify!(
-      map!(
-        take_till1!(|b: u8| is_space(b) || RESERVED_NAME_BYTES.contains(&b)),
-        |bytes| str::from_utf8(&bytes).unwrap()
-      ),
-      |s| !RESERVED_NAMES.contains(&s)
-    ),
-    |s| Name::new(s)
-  ));
+/// A name starts with a letter or `_`, and continues with letters, digits or `_`, following
+/// Unicode's XID rules.
+pub fn name(input: &[u8]) -> IResult<&[u8], Name> {
+    let text = utf8_prefix(input);
+    let mut chars = text.char_indices();
+    match chars.next() {
+        Some((_, c)) if c == '_' || c.is_xid_start() => {}
+        Some(_) => return IResult::Error(ErrorKind::Custom(0)),
+        None if input.is_empty() => return IResult::Incomplete(Needed::Size(1)),
+        None => return IResult::Error(ErrorKind::Custom(0)),
+    }
+    let end = chars
+        .find(|&(_, c)| !c.is_xid_continue())
+        .map_or(text.len(), |(i, _)| i);
+    let name = &text[..end];
+    if RESERVED_NAMES.contains(&name) {
+        return IResult::Error(ErrorKind::Custom(0));
+    }
+    let rest = &input[end..];
+    IResult::Done(rest, Name(name.to_string(), span(input, rest)))
+}
+
+/// A keyword, such as `if`, as long as it isn't the start of a longer name. Any blank may
+/// follow it, so it is left for `ws!` to skip.
+pub fn keyword<'a>(input: &'a [u8], keyword: &str) -> IResult<&'a [u8], &'a [u8]> {
+    let length = keyword.len();
+    if input.len() < length && keyword.as_bytes().starts_with(input) {
+        return IResult::Incomplete(Needed::Size(length));
+    }
+    if !input.starts_with(keyword.as_bytes()) {
+        return IResult::Error(ErrorKind::Tag);
+    }
+    match utf8_prefix(&input[length..]).chars().next() {
+        Some(c) if c.is_xid_continue() => IResult::Error(ErrorKind::Tag),
+        _ => IResult::Done(&input[length..], &input[..length]),
+    }
+}
+
+// The longest prefix of the input that is valid UTF-8.
+fn utf8_prefix(input: &[u8]) -> &str {
+    match str::from_utf8(input) {
+        Ok(text) => text,
+        Err(e) => str::from_utf8(&input[..e.valid_up_to()]).unwrap(),
+    }
+}
 
 named!(pub names<&[u8], Vec<Name>>,
   do_parse!(
@@ -27,3 +63,38 @@ named!(pub names<&[u8], Vec<Name>>,
         v
       }
     })));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_test() {
+        assert_eq!(name(b"x1 "), IResult::Done(&b" "[..], Name::new("x1")));
+        assert_eq!(name(b"_0)"), IResult::Done(&b")"[..], Name::new("_0")));
+        assert_eq!(name(b"a-b"), IResult::Done(&b"-b"[..], Name::new("a")));
+        assert_eq!(
+            name("\u{3b1}\u{394}x\u{2032} ".as_bytes()),
+            IResult::Done(&"\u{2032} ".as_bytes()[..], Name::new("\u{3b1}\u{394}x"))
+        );
+        assert!(name(b"1x").is_err());
+        assert!(name("\u{2032}".as_bytes()).is_err());
+        assert!(name(b"match ").is_err());
+        assert!(name(b"\xffx").is_err());
+        assert_eq!(name(b"a\xff"), IResult::Done(&b"\xff"[..], Name::new("a")));
+    }
+
+    #[test]
+    fn keyword_test() {
+        assert_eq!(keyword(b"if x", "if"), IResult::Done(&b" x"[..], &b"if"[..]));
+        assert_eq!(keyword(b"if(x)", "if"), IResult::Done(&b"(x)"[..], &b"if"[..]));
+        assert_eq!(keyword(b"if\n", "if"), IResult::Done(&b"\n"[..], &b"if"[..]));
+        assert_eq!(keyword(b"if#", "if"), IResult::Done(&b"#"[..], &b"if"[..]));
+        assert_eq!(keyword(b"if", "if"), IResult::Done(&b""[..], &b"if"[..]));
+        assert!(keyword(b"iffy", "if").is_err());
+        assert!(keyword(b"if_", "if").is_err());
+        assert!(keyword("if\u{3b1}".as_bytes(), "if").is_err());
+        assert!(keyword(b"x", "if").is_err());
+        assert_eq!(keyword(b"i", "if"), IResult::Incomplete(Needed::Size(2)));
+    }
+}
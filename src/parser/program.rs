@@ -3,24 +3,26 @@ use super::super::*;
 
 named!(pub inputs<&[u8], Vec<Name>>,
   do_parse!(
-    ws!(tag!("inputs")) >>
+    ws!(call!(keyword, "inputs")) >>
     input_names: call!(names) >>
-    ws!(tag!(";")) >>
+    // Leaves what follows, which may be the first item's doc comment.
+    call!(blank) >>
+    tag!(";") >>
     (input_names)
   ));
 
 named!(pub outputs<&[u8], Vec<Name>>,
   do_parse!(
-    ws!(tag!("outputs")) >>
+    ws!(call!(keyword, "outputs")) >>
     output_names: call!(names) >>
     ws!(tag!(";")) >>
     (output_names)
   ));
 
-named!(pub program<&[u8], Program>,
+named!(pub program<&[u8], (Vec<Name>, Vec<(Option<String>, Item)>, Vec<Name>)>,
   do_parse!(
     inputs: call!(inputs) >>
-    statements: call!(statements) >>
+    items: call!(items) >>
     outputs: call!(outputs) >>
-    (Program::new(inputs, statements, outputs))
+    (inputs, items, outputs)
   ));
@@ -7,23 +7,29 @@ pub struct ShuntingYard {
 }
 
 impl ShuntingYard {
-    pub fn new(first_operand: Operand) -> ShuntingYard {
+    pub fn new(first_operand: Expression) -> ShuntingYard {
         ShuntingYard {
             operator_stack: vec![],
-            expr_stack: vec![Expression::Operand(first_operand)],
+            expr_stack: vec![first_operand],
         }
     }
 
-    pub fn push(&mut self, operator: Operator, operand: Operand) {
+    pub fn push(&mut self, operator: Operator, operand: Expression) {
         loop {
             let end_operator = self.operator_stack.last().cloned();
             match end_operator {
-                Some(end_operator) if end_operator >= operator => self.make_a_tree(),
+                Some(end_operator)
+                    if end_operator.precedence() > operator.precedence()
+                        || (end_operator.precedence() == operator.precedence()
+                            && !operator.is_right_associative()) =>
+                {
+                    self.make_a_tree()
+                }
                 _ => break,
             }
         }
         self.operator_stack.push(operator);
-        self.expr_stack.push(Expression::Operand(operand));
+        self.expr_stack.push(operand);
     }
 
     fn make_a_tree(&mut self) {
@@ -55,7 +61,7 @@ mod tests {
     fn lone_operand_test() {
         let first_operand = Operand::I64(-3);
 
-        let shunting_yard = ShuntingYard::new(first_operand.clone());
+        let shunting_yard = ShuntingYard::new(Expression::Operand(first_operand.clone()));
         assert_eq!(
             shunting_yard.expr_stack,
             vec![Expression::Operand(first_operand.clone())]
@@ -71,14 +77,14 @@ mod tests {
         let operator = Operator::Multiply;
         let operands = (Operand::I64(1), Operand::I64(2));
 
-        let mut shunting_yard = ShuntingYard::new(operands.0.clone());
+        let mut shunting_yard = ShuntingYard::new(Expression::Operand(operands.0.clone()));
         assert_eq!(
             shunting_yard.expr_stack,
             vec![Expression::Operand(operands.0.clone())]
         );
         assert_eq!(shunting_yard.operator_stack, vec![]);
 
-        shunting_yard.push(operator, operands.1.clone());
+        shunting_yard.push(operator, Expression::Operand(operands.1.clone()));
         assert_eq!(
             shunting_yard.expr_stack,
             vec![
@@ -108,14 +114,14 @@ mod tests {
             Operand::I64(11),
         );
 
-        let mut shunting_yard = ShuntingYard::new(tokens.0.clone());
+        let mut shunting_yard = ShuntingYard::new(Expression::Operand(tokens.0.clone()));
         assert_eq!(
             shunting_yard.expr_stack,
             vec![Expression::Operand(tokens.0.clone())]
         );
         assert_eq!(shunting_yard.operator_stack, vec![]);
 
-        shunting_yard.push(tokens.1, tokens.2.clone());
+        shunting_yard.push(tokens.1, Expression::Operand(tokens.2.clone()));
         assert_eq!(
             shunting_yard.expr_stack,
             vec![
@@ -125,7 +131,7 @@ mod tests {
         );
         assert_eq!(shunting_yard.operator_stack, vec![tokens.1]);
 
-        shunting_yard.push(tokens.3, tokens.4.clone());
+        shunting_yard.push(tokens.3, Expression::Operand(tokens.4.clone()));
         assert_eq!(
             shunting_yard.expr_stack,
             vec![
@@ -153,10 +159,66 @@ mod tests {
         );
     }
 
+    #[test]
+    fn associativity_test() {
+        let mut shunting_yard = ShuntingYard::new(Expression::Operand(Operand::I64(1)));
+        shunting_yard.push(Operator::Subtract, Expression::Operand(Operand::I64(2)));
+        shunting_yard.push(Operator::Subtract, Expression::Operand(Operand::I64(3)));
+        assert_eq!(
+            shunting_yard.into_expression(),
+            Expression::Operation(
+                Operator::Subtract,
+                box Expression::Operation(
+                    Operator::Subtract,
+                    box Expression::Operand(Operand::I64(1)),
+                    box Expression::Operand(Operand::I64(2))
+                ),
+                box Expression::Operand(Operand::I64(3))
+            )
+        );
+
+        let mut shunting_yard = ShuntingYard::new(Expression::Operand(Operand::I64(1)));
+        shunting_yard.push(Operator::Subtract, Expression::Operand(Operand::I64(2)));
+        shunting_yard.push(Operator::Add, Expression::Operand(Operand::I64(3)));
+        assert_eq!(
+            shunting_yard.into_expression(),
+            Expression::Operation(
+                Operator::Add,
+                box Expression::Operation(
+                    Operator::Subtract,
+                    box Expression::Operand(Operand::I64(1)),
+                    box Expression::Operand(Operand::I64(2))
+                ),
+                box Expression::Operand(Operand::I64(3))
+            )
+        );
+
+        let mut shunting_yard = ShuntingYard::new(Expression::Operand(Operand::I64(2)));
+        shunting_yard.push(Operator::Power, Expression::Operand(Operand::I64(3)));
+        shunting_yard.push(Operator::Power, Expression::Operand(Operand::I64(2)));
+        shunting_yard.push(Operator::Multiply, Expression::Operand(Operand::I64(4)));
+        assert_eq!(
+            shunting_yard.into_expression(),
+            Expression::Operation(
+                Operator::Multiply,
+                box Expression::Operation(
+                    Operator::Power,
+                    box Expression::Operand(Operand::I64(2)),
+                    box Expression::Operation(
+                        Operator::Power,
+                        box Expression::Operand(Operand::I64(3)),
+                        box Expression::Operand(Operand::I64(2))
+                    )
+                ),
+                box Expression::Operand(Operand::I64(4))
+            )
+        );
+    }
+
     fn shunts_correctly_prop(input: Expression) -> bool {
         let tokens = input.tokens();
         let mut shunting_yard = match tokens[0].clone() {
-            Token::Operand(first_operand) => ShuntingYard::new(first_operand),
+            Token::Operand(first_operand) => ShuntingYard::new(Expression::Operand(first_operand)),
             _ => unreachable!(),
         };
         for chunk in tokens[1..].chunks(2) {
@@ -164,7 +226,7 @@ mod tests {
                 &[Token::Operator(operator), Token::Operand(ref operand)] => (operator, operand),
                 _ => unreachable!(),
             };
-            shunting_yard.push(operator, operand.clone());
+            shunting_yard.push(operator, Expression::Operand(operand.clone()));
         }
         let output = shunting_yard.into_expression();
         format!("{}", input) == format!("{}", output)
@@ -0,0 +1,160 @@
+use super::*;
+use std::cell::RefCell;
+use std::mem;
+
+thread_local! {
+    // The lines of the source being parsed, which tell where the parsers' input is.
+    static SOURCE: RefCell<Option<Lines>> = RefCell::new(None);
+}
+
+// Where a source is, and where its lines and characters start, so that spans can be found
+// without going over the source again.
+struct Lines {
+    address: usize,
+    length: usize,
+    // The offset each line starts at.
+    starts: Vec<usize>,
+    // The offsets of bytes within characters, rather than at their start, which the source
+    // only has if it isn't all ASCII.
+    continuations: Vec<usize>,
+}
+
+impl Lines {
+    fn new(source: &[u8]) -> Lines {
+        let mut starts = vec![0];
+        let mut continuations = vec![];
+        for (i, &b) in source.iter().enumerate() {
+            if b == b'\n' {
+                starts.push(i + 1);
+            } else if b & 0xc0 == 0x80 {
+                continuations.push(i);
+            }
+        }
+        Lines {
+            address: source.as_ptr() as usize,
+            length: source.len(),
+            starts: starts,
+            continuations: continuations,
+        }
+    }
+
+    // The span between two byte offsets, as `Span::new` gives for the source.
+    fn span(&self, start: usize, end: usize) -> Span {
+        let line = match self.starts.binary_search(&start) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        let line_start = self.starts[line];
+        let continuations_before = |offset| match self.continuations.binary_search(&offset) {
+            Ok(i) | Err(i) => i,
+        };
+        let continuations = continuations_before(start) - continuations_before(line_start);
+        Span {
+            start: start,
+            end: end,
+            line: line + 1,
+            column: start - line_start - continuations + 1,
+        }
+    }
+}
+
+/// Runs `parse` with `source` as what `span` finds the parsers' input in. The source must be
+/// valid UTF-8.
+pub fn with_source<T, F: FnOnce() -> T>(source: &[u8], parse: F) -> T {
+    let lines = Some(Lines::new(source));
+    let outer = SOURCE.with(|s| mem::replace(&mut *s.borrow_mut(), lines));
+    let result = parse();
+    SOURCE.with(|s| *s.borrow_mut() = outer);
+    result
+}
+
+/// The span of what was parsed from `input` to leave `rest`, without any blanks around it.
+/// Input that isn't from the source, as when a parser is used on its own, has the default
+/// span.
+pub fn span(input: &[u8], rest: &[u8]) -> Span {
+    SOURCE.with(|source| match *source.borrow() {
+        Some(ref lines) => {
+            let start = (input.as_ptr() as usize).wrapping_sub(lines.address);
+            if start > lines.length || start + input.len() != lines.length {
+                return Span::default();
+            }
+            let parsed = &input[..input.len() - rest.len()];
+            let (leading, content) = trim_blank(parsed);
+            lines.span(start + leading, start + leading + content)
+        }
+        None => Span::default(),
+    })
+}
+
+/// Runs `parser`, also giving the span of what it parsed.
+pub fn with_span<'a, O, F>(input: &'a [u8], parser: F) -> IResult<&'a [u8], (O, Span)>
+where
+    F: Fn(&'a [u8]) -> IResult<&'a [u8], O>,
+{
+    match parser(input) {
+        IResult::Done(rest, output) => IResult::Done(rest, (output, span(input, rest))),
+        IResult::Error(e) => IResult::Error(e),
+        IResult::Incomplete(n) => IResult::Incomplete(n),
+    }
+}
+
+// The length of the blanks before the text, and of the text up to the blanks after it.
+fn trim_blank(text: &[u8]) -> (usize, usize) {
+    let mut rest = text;
+    let mut leading = None;
+    let mut end = 0;
+    loop {
+        rest = match blank(rest) {
+            IResult::Done(rest, _) => rest,
+            _ => rest,
+        };
+        let offset = text.len() - rest.len();
+        if rest.is_empty() {
+            let leading = leading.unwrap_or(offset);
+            return (leading, end.max(leading) - leading);
+        }
+        leading = leading.or(Some(offset));
+        rest = &rest[1..];
+        end = offset + 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn span_test() {
+        let source = "a = 1;\n  /* b */ \u{3b1} + b # c\n;".as_bytes();
+        let sum = &source[7..];
+        let rest = &source[source.len() - 1..];
+        let sum_span = with_source(source, || span(sum, rest));
+        assert_eq!(
+            (sum_span.start, sum_span.end, sum_span.line, sum_span.column),
+            (17, 23, 2, 11)
+        );
+        let name_span = with_source(source, || span(source, &source[1..]));
+        assert_eq!((name_span.start, name_span.end), (0, 1));
+
+        // Input from elsewhere has no span.
+        let copy = source.to_vec();
+        let unknown = with_source(source, || span(&copy[7..], &copy[copy.len() - 1..]));
+        assert_eq!(unknown.line, 0);
+        assert_eq!(span(sum, rest).line, 0);
+    }
+
+    #[test]
+    fn lines_agree_with_span_new() {
+        let source = "\u{3b1}b\n\n  \u{394}x = 1;\nc\n".as_bytes();
+        let lines = Lines::new(source);
+        for start in 0..source.len() + 1 {
+            let (expected, found) = (Span::new(source, start, start), lines.span(start, start));
+            assert_eq!(
+                (found.line, found.column),
+                (expected.line, expected.column),
+                "at {}",
+                start
+            );
+        }
+    }
+}
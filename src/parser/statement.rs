@@ -1,16 +1,79 @@
 use super::*;
 use super::super::*;
+use super::Error;
 
-named!(pub statements<&[u8], Statements>,
-  map!(complete!(many0!(ws!(call!(statement)))), Statements));
+/// A statement as written, before the clauses of piecewise functions are grouped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Item {
+    Statement(Statement),
+    FnClause(FnClause),
+}
+
+impl Item {
+    pub fn into_statement(self) -> Statement {
+        match self {
+            Item::Statement(statement) => statement,
+            Item::FnClause(clause) => desugar_clauses(vec![clause]),
+        }
+    }
+}
+
+/// One clause of a function definition, such as `fact(0) = 1`. Parameters that are plain
+/// names are parsed as `let` bindings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FnClause {
+    pub name: Name,
+    pub params: Vec<Matcher>,
+    pub body: Expression,
+    pub bindings: Vec<(Name, Expression)>,
+    pub span: Span,
+}
+
+impl FnClause {
+    fn param_names(&self) -> Option<Vec<Name>> {
+        self.params
+            .iter()
+            .map(|param| match param {
+                &Matcher::Binding(ref name, ref matcher) if **matcher == Matcher::Wildcard => {
+                    Some(name.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    fn is_irrefutable(&self) -> bool {
+        self.params.iter().all(matches_anything)
+    }
+}
+
+named!(pub items<&[u8], Vec<(Option<String>, Item)>>,
+  complete!(many0!(call!(documented_item))));
+
+// An item with its doc comment.
+named!(pub documented_item<&[u8], (Option<String>, Item)>,
+  pair!(call!(doc_comment), call!(item)));
 
 named!(pub statement<&[u8], Statement>,
+  map!(terminated!(call!(item), call!(blank)), Item::into_statement));
+
+named!(pub item<&[u8], Item>,
   do_parse!(
-    statement: alt_complete!(
-      map!(variable_assignment, |t| Statement::VarAssignment(t.0, t.1)) |
-      map!(function_definition, |t| Statement::FnDefinition(t.0, t.1, t.2))) >>
-    ws!(tag!(";")) >>
-    (statement)));
+    item: alt_complete!(
+      map!(
+        call!(with_span, variable_assignment),
+        |((name, expression), span)| {
+            Item::Statement(Statement::VarAssignment(name, expression, span))
+        }
+      ) |
+      map!(
+        call!(with_span, function_definition),
+        |(clause, span)| Item::FnClause(FnClause { span: span, ..clause })
+      )) >>
+    // Leaves what follows, which may be the next item's doc comment.
+    call!(blank) >>
+    tag!(";") >>
+    (item)));
 
 named!(variable_assignment<&[u8], (Name, Expression)>,
   do_parse!(
@@ -20,16 +83,311 @@ named!(variable_assignment<&[u8], (Name, Expression)>,
     peek!(ws!(tag!(";"))) >>
     (name, expression)));
 
-named!(function_definition<&[u8], (Name, Vec<Name>, Expression)>,
+named!(function_definition<&[u8], FnClause>,
   do_parse!(
     name: call!(name) >>
     ws!(tag!("(")) >>
-    parameters: call!(names) >>
+    parameters: separated_list!(ws!(tag!(",")), call!(parameter)) >>
     ws!(tag!(")")) >>
     ws!(tag!("=")) >>
     expression: call!(expression) >>
+    bindings: map!(opt!(complete!(call!(where_clause))), Option::unwrap_or_default) >>
     peek!(ws!(tag!(";"))) >>
-    (name, parameters, expression)));
+    (FnClause {
+        name: name,
+        params: parameters,
+        body: expression,
+        bindings: bindings,
+        span: Span::default(),
+    })));
+
+named!(parameter<&[u8], Matcher>,
+  alt!(
+    map!(call!(matcher), |matcher| match variable_of(&matcher) {
+        Some(name) => Matcher::Binding(name, box Matcher::Wildcard),
+        None => matcher,
+    }) |
+    map!(ws!(tag!("_")), |_| Matcher::Wildcard)));
+
+// The name a matcher would match, if it's just a variable.
+fn variable_of(matcher: &Matcher) -> Option<Name> {
+    match *matcher {
+        Matcher::Value(ref value) => match *value.unspanned() {
+            Expression::Operand(Operand::VarSubstitution(ref name)) => Some(name.clone()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+named!(where_clause<&[u8], Vec<(Name, Expression)>>,
+  preceded!(
+    ws!(call!(keyword, "where")),
+    separated_nonempty_list!(ws!(tag!(",")), call!(let_binding))));
+
+/// Groups the clauses of piecewise functions into single definitions. A definition whose
+/// parameters are all names stands alone, and redefines any earlier function of that name
+/// whose clauses match any arguments. Otherwise it begins a group that takes in the directly
+/// following clauses for the same function, up to and including the first that matches any
+/// arguments. Clauses directly after such a group could never be used, and clauses of a
+/// function whose earlier clauses don't match every argument must follow them directly, so
+/// both are errors, even for definitions whose parameters are all names. The doc comments of
+/// a group's clauses are joined.
+pub fn group_clauses(items: Vec<(Option<String>, Item)>) -> Result<Statements, Error> {
+    let mut errors = vec![];
+    let statements = group_clauses_recovering(items, &mut errors);
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(statements),
+    }
+}
+
+/// Like `group_clauses`, but notes each error and carries on, leaving out clauses with the
+/// wrong number of parameters and those that could never be used.
+pub fn group_clauses_recovering(
+    items: Vec<(Option<String>, Item)>,
+    errors: &mut Vec<Error>,
+) -> Statements {
+    let mut statements = Statements::new(vec![]);
+    let mut group: Vec<FnClause> = vec![];
+    let mut docs = vec![];
+    let mut incomplete = HashSet::new();
+    // The function whose group of clauses was ended by one matching any arguments, if nothing
+    // has come since.
+    let mut closed = None;
+    for (doc, item) in items {
+        let clause = match item {
+            Item::FnClause(clause) => clause,
+            Item::Statement(statement) => {
+                flush_clauses(&mut group, &mut docs, &mut statements, &mut incomplete);
+                statements.0.push(statement);
+                statements.1.push(doc);
+                closed = None;
+                continue;
+            }
+        };
+        if closed.as_ref() == Some(&clause.name) {
+            errors.push(Error::ClauseAfterCatchAll(clause.name));
+            continue;
+        }
+        closed = None;
+        if group.first().map_or(false, |first| first.name == clause.name) {
+            if clause.params.len() != group[0].params.len() {
+                errors.push(Error::ClauseArityMismatch {
+                    name: clause.name,
+                    expected: group[0].params.len(),
+                    found: clause.params.len(),
+                });
+                continue;
+            }
+        } else {
+            flush_clauses(&mut group, &mut docs, &mut statements, &mut incomplete);
+            if incomplete.contains(&clause.name) {
+                errors.push(Error::NonContiguousClauses(clause.name.clone()));
+            }
+        }
+        let complete = clause.is_irrefutable();
+        group.push(clause);
+        docs.extend(doc);
+        if complete {
+            incomplete.remove(&group[0].name);
+            if group.len() > 1 || group[0].param_names().is_none() {
+                closed = Some(group[0].name.clone());
+            }
+            flush_clauses(&mut group, &mut docs, &mut statements, &mut incomplete);
+        }
+    }
+    flush_clauses(&mut group, &mut docs, &mut statements, &mut incomplete);
+    statements
+}
+
+fn flush_clauses(
+    group: &mut Vec<FnClause>,
+    docs: &mut Vec<String>,
+    statements: &mut Statements,
+    incomplete: &mut HashSet<Name>,
+) {
+    if group.is_empty() {
+        return;
+    }
+    if !group.last().unwrap().is_irrefutable() {
+        incomplete.insert(group[0].name.clone());
+    }
+    statements.0.push(desugar_clauses(group.drain(..).collect()));
+    statements.1.push(if docs.is_empty() {
+        None
+    } else {
+        Some(docs.drain(..).collect::<Vec<_>>().join("\n"))
+    });
+}
+
+/// Turns clauses of one function into a `match` on its arguments, which are passed as `_0`,
+/// `_1` and so on, with more leading `_`s if the clauses mention any of those names. Unmatched
+/// arguments give `0`.
+fn desugar_clauses(mut clauses: Vec<FnClause>) -> Statement {
+    if clauses.len() == 1 {
+        if let Some(params) = clauses[0].param_names() {
+            let clause = clauses.pop().unwrap();
+            return Statement::FnDefinition(
+                clause.name,
+                params,
+                clause.body,
+                clause.bindings,
+                clause.span,
+            );
+        }
+    }
+
+    let name = clauses[0].name.clone();
+    let span = clauses[0].span.to(clauses[clauses.len() - 1].span);
+    let params = argument_names(&clauses);
+    let args: Vec<_> = params
+        .iter()
+        .map(|param| Expression::Operand(Operand::VarSubstitution(param.clone())))
+        .collect();
+    let mut matchers = vec![];
+    let mut default = Expression::Operand(Operand::I64(0));
+    for mut clause in clauses {
+        let matcher = if clause.params.len() == 1 {
+            clause.params.pop().unwrap()
+        } else {
+            Matcher::Tuple(clause.params)
+        };
+        if matches_anything(&matcher) {
+            // The default has nothing to bind names, so `let` does instead.
+            let mut bindings: Vec<_> = matcher
+                .bindings()
+                .into_iter()
+                .map(|(i, name)| (name, args[i].clone()))
+                .collect();
+            bindings.extend(clause.bindings);
+            default = with_bindings(bindings, clause.body);
+            break;
+        }
+        matchers.push((matcher, None, with_bindings(clause.bindings, clause.body)));
+    }
+    let mut match_ = Match::new(args, matchers, default);
+    match_.span = span;
+    let body = Expression::Operand(Operand::Match(match_));
+    Statement::FnDefinition(name, params, body, vec![], span)
+}
+
+// Names for the arguments of a function defined by clauses that none of its clauses mention, so
+// that they can't be hidden by, or take the place of, the clauses' own names.
+fn argument_names(clauses: &[FnClause]) -> Vec<Name> {
+    let mut mentioned = HashSet::new();
+    for clause in clauses {
+        for param in &clause.params {
+            matcher_names(param, &mut mentioned);
+        }
+        for &(ref name, ref value) in &clause.bindings {
+            mentioned.insert(name.clone());
+            expression_names(value, &mut mentioned);
+        }
+        expression_names(&clause.body, &mut mentioned);
+    }
+    let mut prefix = "_".to_string();
+    loop {
+        let names: Vec<_> = (0..clauses[0].params.len())
+            .map(|i| Name::new(&format!("{}{}", prefix, i)))
+            .collect();
+        if names.iter().all(|name| !mentioned.contains(name)) {
+            return names;
+        }
+        prefix.push('_');
+    }
+}
+
+// Adds the names of variables read or bound within an expression.
+fn expression_names(expression: &Expression, names: &mut HashSet<Name>) {
+    match *expression {
+        Expression::Operand(ref operand) => operand_names(operand, names),
+        Expression::Operation(_, ref expression1, ref expression2) => {
+            expression_names(expression1, names);
+            expression_names(expression2, names);
+        }
+        Expression::Spanned(_, ref expression) => expression_names(expression, names),
+    }
+}
+
+fn operand_names(operand: &Operand, names: &mut HashSet<Name>) {
+    match *operand {
+        Operand::I64(_) => {}
+        Operand::Group(ref expression) => expression_names(expression, names),
+        Operand::VarSubstitution(ref name) => {
+            names.insert(name.clone());
+        }
+        Operand::FnApplication(_, ref args) => for arg in args {
+            expression_names(arg, names);
+        },
+        Operand::Match(ref match_) => {
+            for value in &match_.with {
+                expression_names(value, names);
+            }
+            for &(ref matcher, ref guard, ref body) in &match_.clauses {
+                matcher_names(matcher, names);
+                if let Some(ref guard) = *guard {
+                    expression_names(guard, names);
+                }
+                expression_names(body, names);
+            }
+            expression_names(&match_.default, names);
+        }
+        Operand::If(ref condition, ref then, ref else_) => {
+            expression_names(condition, names);
+            expression_names(then, names);
+            expression_names(else_, names);
+        }
+        Operand::Not(ref operand) | Operand::Negate(ref operand) => operand_names(operand, names),
+        Operand::Let(ref bindings, ref body) => {
+            for &(ref name, ref value) in bindings {
+                names.insert(name.clone());
+                expression_names(value, names);
+            }
+            expression_names(body, names);
+        }
+    }
+}
+
+fn matcher_names(matcher: &Matcher, names: &mut HashSet<Name>) {
+    match *matcher {
+        Matcher::Value(ref value) => expression_names(value, names),
+        Matcher::Range {
+            ref start, ref end, ..
+        } => {
+            expression_names(start, names);
+            expression_names(end, names);
+        }
+        Matcher::Alternatives(ref matchers) | Matcher::Tuple(ref matchers) => {
+            for matcher in matchers {
+                matcher_names(matcher, names);
+            }
+        }
+        Matcher::Wildcard => {}
+        Matcher::Binding(ref name, ref matcher) => {
+            names.insert(name.clone());
+            matcher_names(matcher, names);
+        }
+    }
+}
+
+fn with_bindings(bindings: Vec<(Name, Expression)>, body: Expression) -> Expression {
+    if bindings.is_empty() {
+        body
+    } else {
+        Expression::Operand(Operand::Let(bindings, box body))
+    }
+}
+
+fn matches_anything(matcher: &Matcher) -> bool {
+    match matcher {
+        &Matcher::Wildcard => true,
+        &Matcher::Binding(_, ref matcher) => matches_anything(matcher),
+        &Matcher::Alternatives(ref matchers) => matchers.iter().any(matches_anything),
+        &Matcher::Tuple(ref matchers) => matchers.iter().all(matches_anything),
+        &Matcher::Value(_) | &Matcher::Range { .. } => false,
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -59,10 +417,57 @@ mod tests {
                         Operator::Multiply,
                         box Expression::Operand(Operand::VarSubstitution(as_name("a"))),
                         box Expression::Operand(Operand::I64(3))
-                    )
+                    ),
+                    vec![],
+                    Span::default()
+                )
+            )
+        );
+    }
+
+    #[test]
+    fn where_clause_definition() {
+        assert_eq!(
+            statement(b"f(x) = a * b where a = x + 1, b = x - 1;"),
+            as_done(
+                b"",
+                Statement::FnDefinition(
+                    as_name("f"),
+                    vec![as_name("x")],
+                    Expression::Operation(
+                        Operator::Multiply,
+                        box Expression::Operand(Operand::VarSubstitution(as_name("a"))),
+                        box Expression::Operand(Operand::VarSubstitution(as_name("b")))
+                    ),
+                    vec![
+                        (
+                            as_name("a"),
+                            Expression::Operation(
+                                Operator::Add,
+                                box Expression::Operand(Operand::VarSubstitution(as_name("x"))),
+                                box Expression::Operand(Operand::I64(1))
+                            ),
+                        ),
+                        (
+                            as_name("b"),
+                            Expression::Operation(
+                                Operator::Subtract,
+                                box Expression::Operand(Operand::VarSubstitution(as_name("x"))),
+                                box Expression::Operand(Operand::I64(1))
+                            ),
+                        ),
+                    ],
+                    Span::default()
                 )
             )
         );
+        assert_eq!(
+            statement(b"f(x) = a\nwhere\n  a = x;"),
+            statement(b"f(x) = a where a = x;")
+        );
+        assert!(statement(b"f(x) = x where;").is_err());
+        assert!(statement(b"f(x) = a wherea = x;").is_err());
+        assert!(statement(b"x = a where a = 1;").is_err());
     }
 
     #[test]
@@ -71,7 +476,13 @@ mod tests {
             function_definition(b"f() = -11;"),
             as_done(
                 b";",
-                (as_name("f"), vec![], Expression::Operand(Operand::I64(-11)))
+                FnClause {
+                    name: as_name("f"),
+                    params: vec![],
+                    body: Expression::Operand(Operand::I64(-11)),
+                    bindings: vec![],
+                    span: Span::default(),
+                }
             )
         );
 
@@ -82,14 +493,114 @@ mod tests {
                 Statement::FnDefinition(
                     as_name("f"),
                     vec![],
-                    Expression::Operand(Operand::I64(-11))
+                    Expression::Operand(Operand::I64(-11)),
+                    vec![],
+                    Span::default()
                 )
             )
         );
     }
 
+    #[test]
+    fn pattern_parameters() {
+        assert_eq!(
+            function_definition(b"f(0, n, _, m @ 1..3) = n;"),
+            as_done(
+                b";",
+                FnClause {
+                    name: as_name("f"),
+                    params: vec![
+                        Matcher::Value(Expression::Operand(Operand::I64(0))),
+                        Matcher::Binding(as_name("n"), box Matcher::Wildcard),
+                        Matcher::Wildcard,
+                        Matcher::Binding(
+                            as_name("m"),
+                            box Matcher::Range {
+                                start: Expression::Operand(Operand::I64(1)),
+                                end: Expression::Operand(Operand::I64(3)),
+                                inclusive: false,
+                            }
+                        ),
+                    ],
+                    body: Expression::Operand(Operand::VarSubstitution(as_name("n"))),
+                    bindings: vec![],
+                    span: Span::default(),
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn piecewise_definitions() {
+        let parsed = items(b"fact(0) = 1; fact(n) = n * fact(n - 1);").unwrap().1;
+        assert_eq!(
+            format!("{}", group_clauses(parsed).unwrap()),
+            "fact(_0) = match _0 { 0 => 1, _ => let n = _0 in n * fact(n - 1), };"
+        );
+
+        let parsed = items(b"f(0, n) = n; f(m, 1 | 2) = m; g(x) = x; h(_, _) = 3;")
+            .unwrap()
+            .1;
+        assert_eq!(
+            format!("{}", group_clauses(parsed).unwrap()),
+            "f(_0, _1) = match (_0, _1) { (0, let n) => n, (let m, 1 | 2) => m, _ => 0, };\n\
+             g(x) = x;\nh(_0, _1) = match (_0, _1) { _ => 3, };"
+        );
+
+        // Arguments are named so that the clauses' own names can't be mistaken for them.
+        let parsed = items(b"f(0, y) = y; f(_1, _0) = _1 - _0;").unwrap().1;
+        assert_eq!(
+            format!("{}", group_clauses(parsed).unwrap()),
+            "f(__0, __1) = match (__0, __1) { (0, let y) => y, \
+             _ => let _1 = __0, _0 = __1 in _1 - _0, };"
+        );
+    }
+
+    #[test]
+    fn piecewise_definition_errors() {
+        // A definition with only named parameters can't redefine a function whose clauses are
+        // unfinished, as it would be taken for one of them.
+        let parsed = items(b"f(0) = 1; x = 2; f(n) = n;").unwrap().1;
+        assert_eq!(
+            group_clauses(parsed),
+            Err(Error::NonContiguousClauses(as_name("f")))
+        );
+
+        let parsed = items(b"f(0) = 1; f(n, m) = n;").unwrap().1;
+        assert_eq!(
+            group_clauses(parsed),
+            Err(Error::ClauseArityMismatch {
+                name: as_name("f"),
+                expected: 1,
+                found: 2,
+            })
+        );
+
+        // Clauses after one that matches anything would never be used.
+        let parsed = items(b"f(0) = 1; f(n) = n; f(1) = 2;").unwrap().1;
+        assert_eq!(
+            group_clauses(parsed),
+            Err(Error::ClauseAfterCatchAll(as_name("f")))
+        );
+        let parsed = items(b"f(_) = n; f(0) = 1;").unwrap().1;
+        assert_eq!(
+            group_clauses(parsed),
+            Err(Error::ClauseAfterCatchAll(as_name("f")))
+        );
+
+        // Definitions with only named parameters are complete, so may be redefined.
+        let parsed = items(b"f(n) = 1; x = 2; f(0) = 2; f(n) = n;").unwrap().1;
+        assert_eq!(group_clauses(parsed).unwrap().0.len(), 3);
+        let parsed = items(b"f(x) = 1; f(y) = 2;").unwrap().1;
+        assert_eq!(group_clauses(parsed).unwrap().0.len(), 2);
+        let parsed = items(b"f() = 1; f() = 2;").unwrap().1;
+        assert_eq!(group_clauses(parsed).unwrap().0.len(), 2);
+        let parsed = items(b"f(n) = n; f(0) = 1; f(n) = 2;").unwrap().1;
+        assert_eq!(group_clauses(parsed).unwrap().0.len(), 2);
+    }
+
     fn as_name(s: &str) -> Name {
-        Name(s.to_string())
+        Name::new(s)
     }
 
     fn as_done<O, E>(remaining: &[u8], output: O) -> IResult<&[u8], O, E> {